@@ -21,3 +21,90 @@ function! gnvim#enable_ext_popupmenu(enable)
                 \ 'EnableExtPopupmenu',
                 \ a:enable)
 endfunction
+
+function! gnvim#enable_ext_messages(enable)
+    return rpcnotify(
+                \ g:gnvim_channel_id,
+                \ 'Gnvim',
+                \ 'EnableExtMessages',
+                \ a:enable)
+endfunction
+
+function! gnvim#enable_ext_multigrid(enable)
+    return rpcnotify(
+                \ g:gnvim_channel_id,
+                \ 'Gnvim',
+                \ 'EnableExtMultigrid',
+                \ a:enable)
+endfunction
+
+function! gnvim#new_window()
+    return rpcnotify(
+                \ g:gnvim_channel_id,
+                \ 'Gnvim',
+                \ 'NewWindow')
+endfunction
+
+function! gnvim#open()
+    return rpcnotify(
+                \ g:gnvim_channel_id,
+                \ 'Gnvim',
+                \ 'Open')
+endfunction
+
+function! gnvim#dir_changed()
+    " Gnvim might have detached from us (see `--server`).
+    if !exists('g:gnvim_channel_id')
+                \ || empty(nvim_get_chan_info(g:gnvim_channel_id))
+        return 0
+    endif
+
+    return rpcnotify(
+                \ g:gnvim_channel_id,
+                \ 'Gnvim',
+                \ 'DirChanged',
+                \ getcwd())
+endfunction
+
+" Returns gnvim's api info: the api version and the supported events,
+" requests and features. Older gnvims, which don't know about the api info,
+" get version 0 and nothing supported. The info is cached for the gnvim on
+" the other end of `g:gnvim_channel_id`, which changes when another gnvim
+" attaches (see `--server`).
+function! gnvim#api_info()
+    if !exists('s:api_info') || s:api_info_channel != g:gnvim_channel_id
+        try
+            let s:api_info = rpcrequest(g:gnvim_channel_id, 'Gnvim', 'ApiInfo')
+            let s:api_info_channel = g:gnvim_channel_id
+        catch
+            " Not cached, the request might just have failed.
+            unlet! s:api_info
+            return {
+                        \ 'version': 0,
+                        \ 'events': [],
+                        \ 'requests': [],
+                        \ 'features': [],
+                        \ }
+        endtry
+    endif
+
+    return s:api_info
+endfunction
+
+" Returns the names of the highlight groups of the cell under the cursor,
+" including the ui groups (e.g. `CursorLine`) that syntax functions don't
+" know about.
+function! gnvim#cursor_hl_names()
+    return rpcrequest(g:gnvim_channel_id, 'Gnvim', 'CursorHlNames')
+endfunction
+
+function! gnvim#has(feature)
+    if !exists('g:gnvim_channel_id')
+        return 0
+    endif
+
+    let info = gnvim#api_info()
+    return index(info.features, a:feature) >= 0
+                \ || index(info.events, a:feature) >= 0
+                \ || index(info.requests, a:feature) >= 0
+endfunction
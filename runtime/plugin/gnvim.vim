@@ -8,3 +8,13 @@ inoremap <expr> <C-s> gnvim#popupmenu#toggle_details()
 
 command! -nargs=1 GnvimCursorEnableAnimations
             \ call gnvim#cursor#enable_animations(<q-args>)
+
+command! GnvimNewWindow call gnvim#new_window()
+
+command! GnvimOpen call gnvim#open()
+
+" Let gnvim know the current directory (e.g. for the window's subtitle).
+augroup gnvim_cwd
+    autocmd!
+    autocmd VimEnter,DirChanged * call gnvim#dir_changed()
+augroup END
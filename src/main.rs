@@ -23,13 +23,21 @@ extern crate pangocairo;
 #[cfg(feature = "libwebkit2gtk")]
 extern crate webkit2gtk;
 
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+
 use gio::prelude::*;
-use gtk::SettingsExt;
+use gtk::{GtkApplicationExt, GtkWindowExt, SettingsExt};
 
 use log::error;
+use rmpv::Value;
 
 use structopt::{clap, StructOpt};
 
+use nvim_bridge::recording::{self, Recorder};
+
 include!(concat!(env!("OUT_DIR"), "/gnvim_version.rs"));
 
 mod nvim_bridge;
@@ -37,6 +45,9 @@ mod nvim_gio;
 mod thread_guard;
 mod ui;
 
+/// Lowest nvim api level that we work with (nvim 0.4).
+const MIN_API_LEVEL: u64 = 6;
+
 fn parse_geometry(input: &str) -> Result<(i32, i32), String> {
     let ret_tuple: Vec<&str> = input.split('x').collect();
     if ret_tuple.len() != 2 {
@@ -52,7 +63,7 @@ fn parse_geometry(input: &str) -> Result<(i32, i32), String> {
 }
 
 /// Gnvim is a graphical UI for neovim.
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Clone)]
 #[structopt(
     name = "gnvim",
     version = VERSION,
@@ -75,6 +86,53 @@ struct Options {
     )]
     gnvim_rtp: String,
 
+    /// Attach to an already running nvim instead of spawning one. The
+    /// address is either a path to a unix socket or `host:port` (see
+    /// `:h --listen`).
+    #[structopt(long = "server", name = "ADDR", conflicts_with = "nvim_args")]
+    server: Option<String>,
+
+    /// Run nvim on a remote machine over ssh (e.g. `user@host`). The nvim
+    /// binary (see `--nvim`) and the files to open are looked up on the
+    /// remote machine. Gnvim's runtime files are copied over.
+    #[structopt(long = "ssh", name = "DEST", conflicts_with = "ADDR")]
+    ssh: Option<String>,
+
+    /// Record everything nvim sends to the UI into a file. The recording can
+    /// be replayed with `--replay` (e.g. to reproduce rendering bugs).
+    #[structopt(long = "record", name = "RECORD_FILE", parse(from_os_str))]
+    record: Option<std::path::PathBuf>,
+
+    /// Replay a recording made with `--record`. No nvim is started.
+    #[structopt(
+        long = "replay",
+        name = "REPLAY_FILE",
+        parse(from_os_str),
+        conflicts_with_all = &["ADDR", "DEST", "RECORD_FILE"]
+    )]
+    replay: Option<std::path::PathBuf>,
+
+    /// Render the base grid at the end of the `--replay` recording into a
+    /// png file, instead of opening a window. Doesn't need a display.
+    #[structopt(
+        long = "render-png",
+        name = "PNG_FILE",
+        parse(from_os_str),
+        requires = "REPLAY_FILE"
+    )]
+    render_png: Option<std::path::PathBuf>,
+
+    /// Run as a single instance. If gnvim is already running (with
+    /// `--unique`), the files are opened in the running instance instead of
+    /// starting a new one.
+    #[structopt(long = "unique")]
+    unique: bool,
+
+    /// Open the files in a new tab when they're handed to an already running
+    /// instance (see `--unique`).
+    #[structopt(long = "tab", requires = "unique")]
+    tab: bool,
+
     /// Files to open.
     #[structopt(value_name = "FILES")]
     open_files: Vec<String>,
@@ -95,6 +153,14 @@ struct Options {
     #[structopt(long = "disable-ext-tabline")]
     disable_ext_tabline: bool,
 
+    /// Disables externalized messages
+    #[structopt(long = "disable-ext-messages")]
+    disable_ext_messages: bool,
+
+    /// Disables externalized multigrid
+    #[structopt(long = "disable-ext-multigrid")]
+    disable_ext_multigrid: bool,
+
     /// Instruct GTK to prefer dark theme
     #[structopt(long = "gtk-prefer-dark-theme")]
     prefer_dark_theme: bool,
@@ -107,6 +173,14 @@ struct Options {
 enum Error {
     Start(nvim_gio::Error),
     Call(Box<nvim_rs::error::CallError>),
+    Runtime(std::io::Error),
+    Ssh(String, String),
+    Record(std::io::Error),
+    Replay(std::io::Error),
+    RenderPng(std::io::Error),
+    /// Nvim is too old, or doesn't support something that we can't live
+    /// without.
+    Unsupported(String),
 }
 
 impl std::fmt::Display for Error {
@@ -114,6 +188,22 @@ impl std::fmt::Display for Error {
         match self {
             Error::Start(e) => write!(fmt, "Failed to start nvim: {}", e),
             Error::Call(e) => write!(fmt, "Call to nvim failed: {}", e),
+            Error::Runtime(e) => {
+                write!(fmt, "Failed to read gnvim runtime files: {}", e)
+            }
+            Error::Record(e) => {
+                write!(fmt, "Failed to create the recording file: {}", e)
+            }
+            Error::Replay(e) => write!(fmt, "Failed to read recording: {}", e),
+            Error::RenderPng(e) => write!(fmt, "Failed to render png: {}", e),
+            Error::Unsupported(e) => write!(fmt, "Unsupported nvim: {}", e),
+            Error::Ssh(dest, e) => {
+                write!(
+                    fmt,
+                    "Failed to start nvim over ssh on '{}': {}",
+                    dest, e
+                )
+            }
         }
     }
 }
@@ -130,11 +220,280 @@ impl From<Box<nvim_rs::error::CallError>> for Error {
     }
 }
 
-async fn build(app: &gtk::Application, opts: &Options) -> Result<(), Error> {
+/// Starts a new nvim session. On failure, the error is reported along with
+/// what nvim wrote to its stderr.
+///
+/// * `recorder` - Records the session (see `--record`). Shared by all the
+///                sessions of a window, so restarting doesn't lose the
+///                recording so far.
+async fn new_session(
+    opts: &Options,
+    recorder: Option<Arc<Recorder>>,
+) -> Result<
+    (glib::Receiver<nvim_bridge::Message>, nvim_gio::GioNeovim),
+    ui::SessionError,
+> {
+    let stderr = nvim_gio::StderrLog::default();
+
+    match start_session(opts, &stderr, recorder).await {
+        Ok(session) => Ok(session),
+        Err(err) => {
+            // Nvim might still be writing its last words.
+            stderr.wait_closed(nvim_gio::STDERR_TIMEOUT).await;
+
+            Err(ui::SessionError {
+                message: err.to_string(),
+                stderr: stderr.contents(),
+            })
+        }
+    }
+}
+
+/// Starts a new nvim session and attaches to it as an UI.
+async fn start_session(
+    opts: &Options,
+    stderr: &nvim_gio::StderrLog,
+    recorder: Option<Arc<Recorder>>,
+) -> Result<(glib::Receiver<nvim_bridge::Message>, nvim_gio::GioNeovim), Error>
+{
+    if let Some(ref path) = opts.replay {
+        return replay_session(path);
+    }
+
+    let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    let mut bridge = nvim_bridge::NvimBridge::new(tx.clone());
+
+    if let Some(recorder) = recorder {
+        bridge = bridge.with_recorder(recorder);
+    }
+
+    let mut nvim = if let Some(ref addr) = opts.server {
+        attach_nvim(bridge, addr, opts, tx).await?
+    } else {
+        let nvim = spawn_nvim(bridge, opts, tx, stderr)?;
+
+        if let Some(ref dest) = opts.ssh {
+            upload_runtime(&nvim, dest, &opts.gnvim_rtp).await?;
+        }
+
+        nvim
+    };
+
+    nvim.subscribe("Gnvim").await.map_err(Error::from)?;
+
+    let api_info = nvim.get_api_info().await.map_err(Error::from)?;
+    nvim.set_var("gnvim_channel_id", api_info[0].clone())
+        .await
+        .map_err(Error::from)?;
+
+    // Attached nvim is past `VimEnter`, so it won't tell its cwd before it
+    // changes.
+    if let Err(err) = nvim.call_function("gnvim#dir_changed", vec![]).await {
+        error!("Failed to get nvim's cwd: {}", err);
+    }
+
+    let info = nvim_bridge::NvimInfo::try_from(api_info[1].clone()).map_err(
+        |err| Error::Unsupported(format!("failed to read api info: {}", err)),
+    )?;
+    let (ui_opts, disabled) = ui_attach_options(opts, &info)?;
+
+    nvim.ui_attach(80, 30, &ui_opts)
+        .await
+        .map_err(Error::from)?;
+
+    if !disabled.is_empty() {
+        let msg = format!(
+            "Gnvim: nvim {} doesn't support {}, so those features are \
+             disabled. Consider upgrading nvim.\n",
+            info.version,
+            disabled.join(", ")
+        );
+        if let Err(err) = nvim.out_write(&msg).await {
+            error!("Failed to tell about the disabled features: {}", err);
+        }
+    }
+
+    Ok((rx, nvim))
+}
+
+/// Picks the ui options to attach with. Options that nvim doesn't support
+/// are left out, and returned as the second value.
+fn ui_attach_options(
+    opts: &Options,
+    info: &nvim_bridge::NvimInfo,
+) -> Result<(nvim_rs::UiAttachOptions, Vec<&'static str>), Error> {
+    if info.api_level < MIN_API_LEVEL
+        || !info.supports_ui_option("ext_linegrid")
+    {
+        return Err(Error::Unsupported(format!(
+            "nvim {} (api level {}) is too old, gnvim needs nvim 0.4 or newer",
+            info.version, info.api_level
+        )));
+    }
+
+    let mut disabled = vec![];
+    let mut want = |name: &'static str, enable: bool| {
+        if !enable {
+            return false;
+        }
+
+        let supported = info.supports_ui_option(name);
+        if !supported {
+            disabled.push(name);
+        }
+        supported
+    };
+
+    let mut ui_opts = nvim_rs::UiAttachOptions::new();
+    ui_opts.set_rgb(true);
+    ui_opts.set_linegrid_external(true);
+    ui_opts.set_multigrid_external(want(
+        "ext_multigrid",
+        !opts.disable_ext_multigrid,
+    ));
+    ui_opts.set_hlstate_external(want("ext_hlstate", true));
+    ui_opts.set_popupmenu_external(want(
+        "ext_popupmenu",
+        !opts.disable_ext_popupmenu,
+    ));
+    ui_opts
+        .set_tabline_external(want("ext_tabline", !opts.disable_ext_tabline));
+    let cmdline = want("ext_cmdline", !opts.disable_ext_cmdline);
+    ui_opts.set_cmdline_external(cmdline);
+    // ext_messages implies ext_cmdline, so it can't be enabled without it.
+    ui_opts.set_messages_external(
+        cmdline && want("ext_messages", !opts.disable_ext_messages),
+    );
+
+    Ok((ui_opts, disabled))
+}
+
+/// Replays a recording made with `--record`. The notifies are sent to the UI
+/// at the same pace as they were recorded.
+fn replay_session(
+    path: &std::path::Path,
+) -> Result<(glib::Receiver<nvim_bridge::Message>, nvim_gio::GioNeovim), Error>
+{
+    let file = std::fs::File::open(path).map_err(Error::Replay)?;
+    let notifies = recording::read_recording(std::io::BufReader::new(file))
+        .map_err(Error::Replay)?;
+
     let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
-    let bridge = nvim_bridge::NvimBridge::new(tx.clone());
+    let nvim = nvim_gio::new_offline(nvim_bridge::NvimBridge::new(tx.clone()))?;
+
+    let c = glib::MainContext::default();
+    c.spawn_local(async move {
+        let start = Instant::now();
+
+        for notify in notifies {
+            let elapsed = start.elapsed();
+            if notify.time > elapsed {
+                let wait = (notify.time - elapsed).as_millis() as u32;
+                glib::timeout_future(wait).await;
+            }
+
+            match nvim_bridge::parse_notify(&notify.name, notify.args) {
+                Some(n) => {
+                    if tx.send(nvim_bridge::Message::Notify(n)).is_err() {
+                        // The UI is gone.
+                        return;
+                    }
+                }
+                None => error!("Unknown notify in recording: {}", notify.name),
+            }
+        }
+
+        let _ = tx.send(nvim_bridge::Message::Close(nvim_bridge::CloseInfo {
+            reason: String::from("Replay finished"),
+            stderr: None,
+        }));
+    });
+
+    Ok((rx, nvim))
+}
+
+/// Renders the base grid from the recording `replay` into the png file `out`.
+fn render_png(
+    replay: &std::path::Path,
+    out: &std::path::Path,
+) -> Result<(), Error> {
+    let file = std::fs::File::open(replay).map_err(Error::Replay)?;
+    let notifies = recording::read_recording(std::io::BufReader::new(file))
+        .map_err(Error::Replay)?;
+
+    let mut grid = ui::HeadlessGrid::new(1, ui::Font::default(), 0);
+    for notify in notifies {
+        if let Some(nvim_bridge::Notify::RedrawEvent(events)) =
+            nvim_bridge::parse_notify(&notify.name, notify.args)
+        {
+            events
+                .into_iter()
+                .for_each(|event| grid.handle_redraw_event(event));
+        }
+    }
+
+    let mut file = std::fs::File::create(out).map_err(Error::RenderPng)?;
+    grid.write_png(&mut file).map_err(Error::RenderPng)
+}
+
+async fn build(
+    app: &gtk::Application,
+    opts: Rc<Options>,
+) -> Result<(), ui::SessionError> {
+    let recorder = match opts.record {
+        Some(ref path) => {
+            let file = std::fs::File::create(path).map_err(|err| {
+                ui::SessionError {
+                    message: Error::Record(err).to_string(),
+                    stderr: String::new(),
+                }
+            })?;
+            Some(Arc::new(Recorder::new(Box::new(file))))
+        }
+        None => None,
+    };
 
-    let rtp = format!("let &rtp.=',{}'", opts.gnvim_rtp);
+    let (rx, nvim) = new_session(&opts, recorder.clone()).await?;
+
+    let starter_opts = opts.clone();
+    let starter: ui::SessionStarter = Box::new(move || {
+        let opts = starter_opts.clone();
+        let recorder = recorder.clone();
+        Box::pin(async move { new_session(&opts, recorder).await })
+    });
+
+    let ui = ui::UI::init(
+        app,
+        rx,
+        opts.geometry,
+        nvim,
+        starter,
+        opts.server.is_some(),
+        opts.ssh.is_some(),
+        !opts.disable_ext_multigrid,
+    );
+    ui.start();
+
+    Ok(())
+}
+
+/// Opens a new window with its own nvim session. Errors are reported to the
+/// user.
+async fn open_window(app: &gtk::Application, opts: Rc<Options>) {
+    if let Err(err) = build(app, opts).await {
+        error!("Failed to build UI: {}", err.message);
+        ui::show_error(app, None, &err.message, &err.stderr);
+    }
+}
+
+/// Spawns a new embedded nvim, either locally or over ssh.
+fn spawn_nvim(
+    bridge: nvim_bridge::NvimBridge,
+    opts: &Options,
+    tx: glib::Sender<nvim_bridge::Message>,
+    stderr: &nvim_gio::StderrLog,
+) -> Result<nvim_gio::GioNeovim, Error> {
+    let rtp = format!("let &rtp.=',{}'", ui::escape_rtp_item(&opts.gnvim_rtp));
     let mut args: Vec<&str> = vec![
         &opts.nvim_path,
         "--embed",
@@ -142,10 +501,15 @@ async fn build(app: &gtk::Application, opts: &Options) -> Result<(), Error> {
         "let g:gnvim=1",
         "--cmd",
         "set termguicolors",
-        "--cmd",
-        &rtp,
     ];
 
+    // Over ssh, our runtime files aren't available for nvim. Those are
+    // copied over separately (see `upload_runtime`).
+    if opts.ssh.is_none() {
+        args.push("--cmd");
+        args.push(&rtp);
+    }
+
     // Pass arguments from cli to nvim.
     for arg in opts.nvim_args.iter() {
         args.push(arg);
@@ -156,51 +520,173 @@ async fn build(app: &gtk::Application, opts: &Options) -> Result<(), Error> {
         args.push(file);
     }
 
+    let args = match opts.ssh {
+        Some(ref dest) => nvim_gio::ssh_args(dest, &args),
+        None => args.iter().map(|a| a.to_string()).collect(),
+    };
+
     // Print the nvim cmd which is executed if asked.
     if opts.print_nvim_cmd {
         println!("nvim cmd: {:?}", args);
     }
 
-    let mut nvim = nvim_gio::new_child(
+    let nvim = nvim_gio::new_child(
         bridge,
-        args.iter().map(|a| std::ffi::OsStr::new(a)).collect(),
+        args.iter().map(std::ffi::OsStr::new).collect(),
         tx,
-    )
-    .map_err(Error::from)?;
+        stderr,
+    )?;
 
-    nvim.subscribe("Gnvim").await.map_err(Error::from)?;
+    Ok(nvim)
+}
 
-    let api_info = nvim.get_api_info().await.map_err(Error::from)?;
-    nvim.set_var("gnvim_channel_id", api_info[0].clone())
+/// Copies gnvim's runtime files from `rtp` to a temporary directory on the
+/// (remote) machine nvim is running on, and adds that directory to nvim's
+/// runtimepath. Needs to be done before attaching the UI, so nvim's startup
+/// picks up our plugin files. This is also the first time we talk to nvim
+/// over the ssh connection, so connection errors are reported from here.
+async fn upload_runtime(
+    nvim: &nvim_gio::GioNeovim,
+    dest: &str,
+    rtp: &str,
+) -> Result<(), Error> {
+    let ssh_err = |err: Box<nvim_rs::error::CallError>| {
+        Error::Ssh(dest.to_string(), err.to_string())
+    };
+
+    let mut files = vec![];
+    collect_files(std::path::Path::new(rtp), &mut vec![], &mut files)
+        .map_err(Error::Runtime)?;
+
+    let dir = nvim
+        .call_function("tempname", vec![])
         .await
-        .map_err(Error::from)?;
-
-    let mut ui_opts = nvim_rs::UiAttachOptions::new();
-    ui_opts.set_rgb(true);
-    ui_opts.set_linegrid_external(true);
-    ui_opts.set_multigrid_external(true);
-    ui_opts.set_popupmenu_external(!opts.disable_ext_popupmenu);
-    ui_opts.set_tabline_external(!opts.disable_ext_tabline);
-    ui_opts.set_cmdline_external(!opts.disable_ext_cmdline);
+        .map_err(ssh_err)?;
+    let dir = match dir.as_str() {
+        Some(dir) => dir.to_string(),
+        None => {
+            return Err(Error::Ssh(
+                dest.to_string(),
+                format!("unexpected tempname() result: {}", dir),
+            ))
+        }
+    };
+
+    for (path, content) in files {
+        let (parent, name) = path.split_at(path.len() - 1);
+        let parent = std::iter::once(dir.as_str())
+            .chain(parent.iter().map(String::as_str))
+            .collect::<Vec<&str>>()
+            .join("/");
+
+        nvim.call_function("mkdir", vec![parent.clone().into(), "p".into()])
+            .await
+            .map_err(ssh_err)?;
+
+        let lines = String::from_utf8_lossy(&content)
+            .split('\n')
+            .map(Value::from)
+            .collect::<Vec<Value>>();
+        let file = format!("{}/{}", parent, name[0]);
+        nvim.call_function(
+            "writefile",
+            vec![Value::from(lines), file.into(), "b".into()],
+        )
+        .await
+        .map_err(ssh_err)?;
+    }
 
-    nvim.ui_attach(80, 30, &ui_opts)
+    nvim.command(&format!("let &rtp.=',{}'", ui::escape_rtp_item(&dir)))
         .await
-        .map_err(Error::from)?;
+        .map_err(ssh_err)?;
 
-    let ui = ui::UI::init(app, rx, opts.geometry, nvim);
-    ui.start();
+    Ok(())
+}
+
+/// Reads all the files under `dir` into `files`, with their paths split
+/// into components (relative to the original `dir`).
+fn collect_files(
+    dir: &std::path::Path,
+    prefix: &mut Vec<String>,
+    files: &mut Vec<(Vec<String>, Vec<u8>)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        prefix.push(entry.file_name().to_string_lossy().into_owned());
+
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), prefix, files)?;
+        } else {
+            files.push((prefix.clone(), std::fs::read(entry.path())?));
+        }
+
+        prefix.pop();
+    }
 
     Ok(())
 }
 
-fn main() {
-    env_logger::init();
+/// Attaches to an already running nvim listening on `addr`. Since nvim is
+/// already up, the setup that `spawn_nvim` passes through `--cmd` is done
+/// here with regular commands.
+async fn attach_nvim(
+    bridge: nvim_bridge::NvimBridge,
+    addr: &str,
+    opts: &Options,
+    tx: glib::Sender<nvim_bridge::Message>,
+) -> Result<nvim_gio::GioNeovim, Error> {
+    let nvim = nvim_gio::new_connection(bridge, addr, tx).await?;
+
+    nvim.command("let g:gnvim=1").await?;
+    nvim.command("set termguicolors").await?;
+    if !nvim
+        .list_runtime_paths()
+        .await?
+        .iter()
+        .any(|path| path == &opts.gnvim_rtp)
+    {
+        nvim.command(&format!(
+            "let &rtp.=',{}'",
+            ui::escape_rtp_item(&opts.gnvim_rtp)
+        ))
+        .await?;
+    }
+    nvim.command("runtime! plugin/gnvim.vim").await?;
 
-    if let Err(err) = gtk::init() {
-        error!("Failed to initialize gtk: {}", err);
-        return;
+    for file in opts.open_files.iter() {
+        nvim.command(&format!("drop {}", ui::escape_filename(file)))
+            .await?;
+    }
+
+    Ok(nvim)
+}
+
+/// Hands the files to open to the primary instance (see `--unique`), or just
+/// activates it if there aren't any.
+fn forward_to_primary(app: &gtk::Application, opts: &Options) {
+    if opts.open_files.is_empty() {
+        app.activate();
+    } else {
+        let files: Vec<gio::File> = opts
+            .open_files
+            .iter()
+            .map(gio::File::new_for_commandline_arg)
+            .collect();
+        let hint = if opts.tab { ui::OPEN_HINT_TAB } else { "" };
+        app.open(&files, hint);
     }
 
+    // Make sure our message gets through before we exit.
+    if let Some(conn) = app.get_dbus_connection() {
+        if let Err(err) = conn.flush_sync(None::<&gio::Cancellable>) {
+            error!("Failed to reach the running gnvim: {}", err);
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+
     let opts = Options::clap();
     let opts = Options::from_clap(&opts.get_matches_safe().unwrap_or_else(
         |mut err| {
@@ -218,12 +704,41 @@ fn main() {
         },
     ));
 
+    // Rendering to png doesn't need gtk (nor a display).
+    if let (Some(ref replay), Some(ref out)) = (&opts.replay, &opts.render_png)
+    {
+        if let Err(err) = render_png(replay, out) {
+            error!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(err) = gtk::init() {
+        error!("Failed to initialize gtk: {}", err);
+        return;
+    }
+
     let mut flags = gio::ApplicationFlags::empty();
-    flags.insert(gio::ApplicationFlags::NON_UNIQUE);
+    if !opts.unique {
+        flags.insert(gio::ApplicationFlags::NON_UNIQUE);
+    }
     flags.insert(gio::ApplicationFlags::HANDLES_OPEN);
     let app = gtk::Application::new(Some("com.github.vhakulinen.gnvim"), flags)
         .unwrap();
 
+    if opts.unique {
+        if let Err(err) = app.register(None::<&gio::Cancellable>) {
+            error!("Failed to register application: {}", err);
+            return;
+        }
+
+        if app.get_is_remote() {
+            forward_to_primary(&app, &opts);
+            return;
+        }
+    }
+
     gdk::set_program_class("GNvim");
     glib::set_application_name("GNvim");
     gtk::Window::set_default_icon_name("gnvim");
@@ -234,15 +749,75 @@ fn main() {
         }
     }
 
-    app.connect_activate(move |app| {
-        let opts = &opts;
+    let opts = Rc::new(opts);
+
+    // Every new window gets a fresh nvim, without the files from the
+    // command line.
+    let mut window_opts = (*opts).clone();
+    window_opts.open_files.clear();
+    window_opts.server = None;
+    window_opts.record = None;
+    window_opts.replay = None;
+    let window_opts = Rc::new(window_opts);
+
+    // Files forwarded from other gnvim instances (see `--unique`) are opened
+    // in the most recent window. If no window can take them, they get a
+    // window of their own.
+    let open_opts = window_opts.clone();
+    app.connect_open(move |app, files, hint| {
+        if ui::open_files(app, files, hint) {
+            return;
+        }
+
+        let mut opts = (*open_opts).clone();
+        // Files that aren't local are passed as uris, for nvim (e.g. netrw)
+        // to handle.
+        opts.open_files = files
+            .iter()
+            .map(|file| match file.get_path() {
+                Some(path) => path.to_string_lossy().into_owned(),
+                None => file.get_uri().to_string(),
+            })
+            .collect();
+        let opts = Rc::new(opts);
+
+        app.hold();
+        let app = app.clone();
         let c = glib::MainContext::default();
-        c.block_on(async move {
-            if let Err(err) = build(app, opts).await {
-                error!("Failed to build UI: {}", err);
-            }
+        c.spawn_local(async move {
+            open_window(&app, opts).await;
+            app.release();
         });
     });
 
+    let new_window = gio::SimpleAction::new(ui::NEW_WINDOW_ACTION, None);
+    let action_app = app.clone();
+    new_window.connect_activate(move |_, _| {
+        // Keep the application alive while the session is starting, even
+        // if the last window happens to be closed meanwhile.
+        action_app.hold();
+        let app = action_app.clone();
+        let opts = window_opts.clone();
+        let c = glib::MainContext::default();
+        c.spawn_local(async move {
+            open_window(&app, opts).await;
+            app.release();
+        });
+    });
+    app.add_action(&new_window);
+
+    app.connect_activate(move |app| {
+        // We're already running (and another instance was started with
+        // `--unique`).
+        if let Some(win) = app.get_active_window() {
+            win.present();
+            return;
+        }
+
+        let opts = opts.clone();
+        let c = glib::MainContext::default();
+        c.block_on(open_window(app, opts));
+    });
+
     app.run(&[]);
 }
@@ -1,6 +1,7 @@
 use log::{debug, error};
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
@@ -14,75 +15,112 @@ use crate::nvim_gio::GioWriter;
 use crate::thread_guard::ThreadGuard;
 use crate::ui::color::{Color, Highlight};
 
+pub mod recording;
 #[cfg(test)]
 mod tests;
 
-macro_rules! unwrap_str {
-    ($val:expr) => {
-        $val.as_str().unwrap();
-    };
+use self::recording::Recorder;
+
+/// Failure to parse something that nvim sent to us.
+#[derive(Debug, PartialEq)]
+pub struct ParseError(String);
+
+impl ParseError {
+    fn invalid(expected: &str, val: &Value) -> Self {
+        ParseError(format!("expected {}, got '{}'", expected, val))
+    }
 }
 
-macro_rules! unwrap_u64 {
-    ($val:expr) => {
-        $val.as_u64().unwrap();
-    };
+impl fmt::Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+impl From<&str> for ParseError {
+    fn from(arg: &str) -> Self {
+        ParseError(arg.to_string())
+    }
+}
+
+/// Gets `$val` with `$as` (e.g. `as_str`), or returns a `ParseError`.
+macro_rules! try_value {
+    ($val:expr, $as:ident, $expected:expr) => {{
+        let val: &Value = &$val;
+        val.$as()
+            .ok_or_else(|| ParseError::invalid($expected, val))?
+    }};
 }
 
-macro_rules! unwrap_i64 {
+macro_rules! try_i64 {
     ($val:expr) => {
-        $val.as_i64().unwrap();
+        try_value!($val, as_i64, "i64")
     };
 }
 
-macro_rules! unwrap_f64 {
+macro_rules! try_f64 {
     ($val:expr) => {
-        $val.as_f64().unwrap();
+        try_value!($val, as_f64, "f64")
     };
 }
 
-macro_rules! unwrap_array {
+macro_rules! try_array {
     ($val:expr) => {
-        $val.as_array().unwrap();
+        try_value!($val, as_array, "array")
     };
 }
 
-macro_rules! unwrap_map {
+macro_rules! try_map {
     ($val:expr) => {
-        $val.as_map().unwrap();
+        try_value!($val, as_map, "map")
     };
 }
 
-macro_rules! unwrap_bool {
+macro_rules! try_bool {
     ($val:expr) => {
-        $val.as_bool().unwrap();
+        try_value!($val, as_bool, "bool")
     };
 }
 
+/// Without `$msg`, fails with `ParseError`.
 macro_rules! try_str {
+    ($val:expr) => {
+        try_value!($val, as_str, "str")
+    };
     ($val:expr, $msg:expr) => {
         $val.as_str()
             .ok_or(format!("Value is not an str: {}", $msg))?
     };
 }
 
+/// Without `$msg`, fails with `ParseError`.
 macro_rules! try_u64 {
+    ($val:expr) => {
+        try_value!($val, as_u64, "u64")
+    };
     ($val:expr, $msg:expr) => {
         $val.as_u64()
             .ok_or(format!("Value is not an u64: {}", $msg))?
     };
 }
 
+static NIL: Value = Value::Nil;
+
+/// Returns `args[i]`, or nil if `args` is too short.
+fn arg(args: &[Value], i: usize) -> &Value {
+    args.get(i).unwrap_or(&NIL)
+}
+
 impl Highlight {
-    fn from_map_val(map: &[(Value, Value)]) -> Self {
+    fn from_map_val(map: &[(Value, Value)]) -> Result<Self, ParseError> {
         let mut hl = Highlight::default();
         for (prop, val) in map {
-            hl.set(unwrap_str!(prop), val.clone());
+            hl.set(try_str!(prop), val.clone())?;
         }
-        hl
+        Ok(hl)
     }
 
-    fn set(&mut self, prop: &str, val: Value) {
+    fn set(&mut self, prop: &str, val: Value) -> Result<(), ParseError> {
         match prop {
             "foreground" => {
                 self.foreground = if let Some(val) = val.as_u64() {
@@ -106,22 +144,42 @@ impl Highlight {
                 }
             }
             "reverse" => {
-                self.reverse = unwrap_bool!(val);
+                self.reverse = try_bool!(val);
             }
             "italic" => {
-                self.italic = unwrap_bool!(val);
+                self.italic = try_bool!(val);
             }
             "bold" => {
-                self.bold = unwrap_bool!(val);
+                self.bold = try_bool!(val);
             }
             "underline" => {
-                self.underline = unwrap_bool!(val);
+                self.underline = try_bool!(val);
             }
             "undercurl" => {
-                self.undercurl = unwrap_bool!(val);
+                self.undercurl = try_bool!(val);
+            }
+            // Before nvim 0.8, underdouble, underdotted and underdashed were
+            // called underlineline, underdot and underdash.
+            "underdouble" | "underlineline" => {
+                self.underdouble = try_bool!(val);
+            }
+            "underdotted" | "underdot" => {
+                self.underdotted = try_bool!(val);
+            }
+            "underdashed" | "underdash" => {
+                self.underdashed = try_bool!(val);
+            }
+            "strikethrough" => {
+                self.strikethrough = try_bool!(val);
+            }
+            "altfont" => {
+                self.altfont = try_bool!(val);
+            }
+            "nocombine" => {
+                self.nocombine = try_bool!(val);
             }
             "blend" => {
-                self.blend = unwrap_f64!(val) / 100.0;
+                self.blend = try_f64!(val) / 100.0;
             }
             "cterm_fg" => {}
             "cterm_bg" => {}
@@ -129,13 +187,14 @@ impl Highlight {
                 debug!("Unknown highligh property: {}", prop);
             }
         }
+
+        Ok(())
     }
 }
 
 pub enum Notify {
-    /// Redraw event will always get parsed. If something goes wrong there,
-    /// we'll panic. Messages are coming from nvim so we should always be
-    /// able to parse them.
+    /// Redraw events. Events that fail to parse are passed on as
+    /// `RedrawEvent::Malformed`, so the rest of the batch can be applied.
     RedrawEvent(Vec<RedrawEvent>),
     /// Gnvim event might fail parsing, because user can send basically
     /// anything to the ('Gnvim') channel.
@@ -150,14 +209,12 @@ pub enum CursorShape {
 }
 
 impl CursorShape {
-    fn from_string(name: &str) -> Self {
+    fn from_string(name: &str) -> Result<Self, ParseError> {
         match String::from(name).to_lowercase().as_str() {
-            "block" => CursorShape::Block,
-            "horizontal" => CursorShape::Horizontal,
-            "vertical" => CursorShape::Vertical,
-            _ => {
-                panic!("Unknown cursor shape: {}", name);
-            }
+            "block" => Ok(CursorShape::Block),
+            "horizontal" => Ok(CursorShape::Horizontal),
+            "vertical" => Ok(CursorShape::Vertical),
+            _ => Err(ParseError(format!("unknown cursor shape: {}", name))),
         }
     }
 }
@@ -179,16 +236,16 @@ pub struct ModeInfo {
 }
 
 impl ModeInfo {
-    fn set(&mut self, prop: &str, val: Value) {
+    fn set(&mut self, prop: &str, val: Value) -> Result<(), ParseError> {
         match prop {
             "blinkon" => {
-                self.blink_on = unwrap_u64!(val);
+                self.blink_on = try_u64!(val);
             }
             "cursor_shape" => {
-                self.cursor_shape = CursorShape::from_string(unwrap_str!(val))
+                self.cursor_shape = CursorShape::from_string(try_str!(val))?
             }
             "cell_percentage" => {
-                let mut val = unwrap_u64!(val);
+                let mut val = try_u64!(val);
 
                 // Ensure that the val is not zero.
                 if val == 0 {
@@ -198,6 +255,8 @@ impl ModeInfo {
             }
             _ => {}
         }
+
+        Ok(())
     }
 }
 
@@ -213,33 +272,49 @@ pub struct Cell {
 pub enum OptionSet {
     /// Font name.
     GuiFont(String),
+    /// Font name for double width characters.
+    GuiFontWide(String),
     /// Space between lines.
     LineSpace(i64),
     ExtTabline(bool),
     ExtCmdline(bool),
     ExtPopupmenu(bool),
+    ExtMessages(bool),
+    ExtMultigrid(bool),
+    /// Hide the mouse pointer while typing.
+    MouseHide(bool),
+    /// Transparency of the popupmenu, from 0 (opaque) to 100.
+    PumBlend(i64),
+    /// When to show the tabline (0: never, 1: with two or more tabs, 2:
+    /// always).
+    ShowTabline(i64),
     /// Event name.
     NotSupported(String),
 }
 
-impl From<Value> for OptionSet {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let name = unwrap_str!(args[0]);
-        match name {
-            "guifont" => {
-                let val = unwrap_str!(args[1]);
-                OptionSet::GuiFont(String::from(val))
-            }
-            "linespace" => {
-                let val = unwrap_i64!(args[1]);
-                OptionSet::LineSpace(val)
+impl TryFrom<Value> for OptionSet {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let name = try_str!(arg(args, 0));
+        let val = arg(args, 1);
+        Ok(match name {
+            "guifont" => OptionSet::GuiFont(String::from(try_str!(val))),
+            "guifontwide" => {
+                OptionSet::GuiFontWide(String::from(try_str!(val)))
             }
-            "ext_tabline" => OptionSet::ExtTabline(unwrap_bool!(args[1])),
-            "ext_cmdline" => OptionSet::ExtCmdline(unwrap_bool!(args[1])),
-            "ext_popupmenu" => OptionSet::ExtPopupmenu(unwrap_bool!(args[1])),
+            "linespace" => OptionSet::LineSpace(try_i64!(val)),
+            "ext_tabline" => OptionSet::ExtTabline(try_bool!(val)),
+            "ext_cmdline" => OptionSet::ExtCmdline(try_bool!(val)),
+            "ext_popupmenu" => OptionSet::ExtPopupmenu(try_bool!(val)),
+            "ext_messages" => OptionSet::ExtMessages(try_bool!(val)),
+            "ext_multigrid" => OptionSet::ExtMultigrid(try_bool!(val)),
+            "mousehide" => OptionSet::MouseHide(try_bool!(val)),
+            "pumblend" => OptionSet::PumBlend(try_i64!(val)),
+            "showtabline" => OptionSet::ShowTabline(try_i64!(val)),
             _ => OptionSet::NotSupported(String::from(name)),
-        }
+        })
     }
 }
 
@@ -343,24 +418,26 @@ pub struct PopupmenuShow {
     pub grid: i64,
 }
 
-impl From<Value> for PopupmenuShow {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
+impl TryFrom<Value> for PopupmenuShow {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
 
-        let selected = unwrap_i64!(args[1]);
-        let row = unwrap_u64!(args[2]);
-        let col = unwrap_u64!(args[3]);
-        let grid = unwrap_i64!(args[4]);
+        let selected = try_i64!(arg(args, 1));
+        let row = try_u64!(arg(args, 2));
+        let col = try_u64!(arg(args, 3));
+        let grid = try_i64!(arg(args, 4));
 
         let mut items = vec![];
-        for item in unwrap_array!(args[0]) {
-            let item = unwrap_array!(item);
-            let word = unwrap_str!(item[0]).to_owned();
-            let kind = CompletionItemKind::from(unwrap_str!(item[1]));
+        for item in try_array!(arg(args, 0)) {
+            let item = try_array!(item);
+            let word = try_str!(arg(item, 0)).to_owned();
+            let kind = CompletionItemKind::from(try_str!(arg(item, 1)));
 
-            let kind_raw = unwrap_str!(item[1]).to_owned();
-            let menu = unwrap_str!(item[2]).to_owned();
-            let info = unwrap_str!(item[3]).to_owned();
+            let kind_raw = try_str!(arg(item, 1)).to_owned();
+            let menu = try_str!(arg(item, 2)).to_owned();
+            let info = try_str!(arg(item, 3)).to_owned();
 
             items.push(CompletionItem {
                 word,
@@ -371,13 +448,13 @@ impl From<Value> for PopupmenuShow {
             });
         }
 
-        PopupmenuShow {
+        Ok(PopupmenuShow {
             items,
             selected,
             row,
             col,
             grid,
-        }
+        })
     }
 }
 
@@ -391,32 +468,26 @@ pub struct CmdlineShow {
     pub level: u64,
 }
 
-impl From<Value> for CmdlineShow {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let content: Vec<(u64, String)> = unwrap_array!(args[0])
-            .iter()
-            .map(|v| {
-                let hl_id = unwrap_u64!(v[0]);
-                let text = unwrap_str!(v[1]);
+impl TryFrom<Value> for CmdlineShow {
+    type Error = ParseError;
 
-                (hl_id, String::from(text))
-            })
-            .collect();
-        let pos = unwrap_u64!(args[1]);
-        let firstc = String::from(unwrap_str!(args[2]));
-        let prompt = String::from(unwrap_str!(args[3]));
-        let indent = unwrap_u64!(args[4]);
-        let level = unwrap_u64!(args[5]);
-
-        CmdlineShow {
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let content = parse_hl_chunks(arg(args, 0))?;
+        let pos = try_u64!(arg(args, 1));
+        let firstc = String::from(try_str!(arg(args, 2)));
+        let prompt = String::from(try_str!(arg(args, 3)));
+        let indent = try_u64!(arg(args, 4));
+        let level = try_u64!(arg(args, 5));
+
+        Ok(CmdlineShow {
             content,
             pos,
             firstc,
             prompt,
             indent,
             level,
-        }
+        })
     }
 }
 
@@ -428,19 +499,21 @@ pub struct GridLineSegment {
     pub cells: Vec<Cell>,
 }
 
-impl From<Value> for GridLineSegment {
-    fn from(args: Value) -> Self {
-        let entry = unwrap_array!(args);
+impl TryFrom<Value> for GridLineSegment {
+    type Error = ParseError;
 
-        let grid = unwrap_i64!(entry[0]);
-        let row = unwrap_u64!(entry[1]);
-        let col_start = unwrap_u64!(entry[2]);
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let entry = try_array!(args);
+
+        let grid = try_i64!(arg(entry, 0));
+        let row = try_u64!(arg(entry, 1));
+        let col_start = try_u64!(arg(entry, 2));
 
         let mut cells: Vec<Cell> = vec![];
 
-        for entry in unwrap_array!(entry[3]) {
-            let entry = unwrap_array!(entry);
-            let text = unwrap_str!(entry[0]);
+        for entry in try_array!(arg(entry, 3)) {
+            let entry = try_array!(entry);
+            let text = try_str!(arg(entry, 0));
             let hl_id = if entry.len() >= 2 {
                 entry[1].as_u64()
             } else {
@@ -448,15 +521,15 @@ impl From<Value> for GridLineSegment {
             };
 
             let repeat = if entry.len() >= 3 {
-                unwrap_u64!(entry[2])
+                try_u64!(arg(entry, 2))
             } else {
                 1
             };
 
-            let hl_id = if let Some(hl_id) = hl_id {
-                hl_id
-            } else {
-                cells.last().unwrap().hl_id
+            let hl_id = match (hl_id, cells.last()) {
+                (Some(hl_id), _) => hl_id,
+                (None, Some(prev)) => prev.hl_id,
+                (None, None) => return Err("first cell has no hl_id".into()),
             };
 
             if text == "" {
@@ -473,12 +546,12 @@ impl From<Value> for GridLineSegment {
             });
         }
 
-        GridLineSegment {
+        Ok(GridLineSegment {
             grid,
             row,
             col_start,
             cells,
-        }
+        })
     }
 }
 
@@ -489,14 +562,16 @@ pub struct GridResize {
     pub height: u64,
 }
 
-impl From<Value> for GridResize {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        GridResize {
-            grid: unwrap_i64!(args[0]),
-            width: unwrap_u64!(args[1]),
-            height: unwrap_u64!(args[2]),
-        }
+impl TryFrom<Value> for GridResize {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(GridResize {
+            grid: try_i64!(arg(args, 0)),
+            width: try_u64!(arg(args, 1)),
+            height: try_u64!(arg(args, 2)),
+        })
     }
 }
 
@@ -507,14 +582,16 @@ pub struct GridCursorGoto {
     pub col: u64,
 }
 
-impl From<Value> for GridCursorGoto {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        GridCursorGoto {
-            grid: unwrap_i64!(args[0]),
-            row: unwrap_u64!(args[1]),
-            col: unwrap_u64!(args[2]),
-        }
+impl TryFrom<Value> for GridCursorGoto {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(GridCursorGoto {
+            grid: try_i64!(arg(args, 0)),
+            row: try_u64!(arg(args, 1)),
+            col: try_u64!(arg(args, 2)),
+        })
     }
 }
 
@@ -526,17 +603,23 @@ pub struct GridScroll {
     pub cols: i64,
 }
 
-impl From<Value> for GridScroll {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let reg: Vec<u64> = args[1..5].iter().map(|v| unwrap_u64!(v)).collect();
-        let reg = [reg[0], reg[1], reg[2], reg[3]];
-        GridScroll {
-            grid: unwrap_i64!(args[0]),
+impl TryFrom<Value> for GridScroll {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let reg = [
+            try_u64!(arg(args, 1)),
+            try_u64!(arg(args, 2)),
+            try_u64!(arg(args, 3)),
+            try_u64!(arg(args, 4)),
+        ];
+        Ok(GridScroll {
+            grid: try_i64!(arg(args, 0)),
             reg,
-            rows: unwrap_i64!(args[5]),
-            cols: unwrap_i64!(args[6]),
-        }
+            rows: try_i64!(arg(args, 5)),
+            cols: try_i64!(arg(args, 6)),
+        })
     }
 }
 
@@ -547,16 +630,61 @@ pub struct DefaultColorsSet {
     pub sp: Color,
 }
 
-impl From<Value> for DefaultColorsSet {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
+impl TryFrom<Value> for DefaultColorsSet {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
 
-        let fg = Color::from_u64(args[0].as_u64().unwrap_or(0));
-        let bg = Color::from_u64(args[1].as_u64().unwrap_or(std::u64::MAX));
+        let fg = Color::from_u64(arg(args, 0).as_u64().unwrap_or(0));
+        let bg =
+            Color::from_u64(arg(args, 1).as_u64().unwrap_or(std::u64::MAX));
         // Default to red.
-        let sp = Color::from_u64(args[2].as_u64().unwrap_or(16711680));
+        let sp = Color::from_u64(arg(args, 2).as_u64().unwrap_or(16711680));
 
-        DefaultColorsSet { fg, bg, sp }
+        Ok(DefaultColorsSet { fg, bg, sp })
+    }
+}
+
+/// Source of a highlight, from `ext_hlstate` (see `:h ui-hlstate`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HlInfo {
+    /// Either `ui`, `syntax` or `terminal`.
+    pub kind: String,
+    /// Name of the builtin UI highlight (e.g. `StatusLine`). Only for `ui`
+    /// kind.
+    pub ui_name: Option<String>,
+    /// Name of the highlight group, after following links.
+    pub hi_name: Option<String>,
+    /// Id of the highlight group.
+    pub id: Option<u64>,
+}
+
+impl TryFrom<Value> for HlInfo {
+    type Error = ParseError;
+
+    fn try_from(info: Value) -> Result<Self, Self::Error> {
+        let mut kind = String::new();
+        let mut ui_name = None;
+        let mut hi_name = None;
+        let mut id = None;
+
+        for (key, val) in try_map!(info) {
+            match try_str!(key) {
+                "kind" => kind = try_str!(val).to_string(),
+                "ui_name" => ui_name = Some(try_str!(val).to_string()),
+                "hi_name" => hi_name = Some(try_str!(val).to_string()),
+                "id" => id = Some(try_u64!(val)),
+                _ => {}
+            }
+        }
+
+        Ok(HlInfo {
+            kind,
+            ui_name,
+            hi_name,
+            id,
+        })
     }
 }
 
@@ -564,17 +692,32 @@ impl From<Value> for DefaultColorsSet {
 pub struct HlAttrDefine {
     pub id: u64,
     pub hl: Highlight,
+    /// Where the highlight comes from. Empty, unless `ext_hlstate` is
+    /// enabled.
+    pub info: Vec<HlInfo>,
 }
 
-impl From<Value> for HlAttrDefine {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let id = unwrap_u64!(args[0]);
-        let map = unwrap_map!(args[1]);
+impl TryFrom<Value> for HlAttrDefine {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let id = try_u64!(arg(args, 0));
+        let map = try_map!(arg(args, 1));
 
-        let hl = Highlight::from_map_val(map);
+        let hl = Highlight::from_map_val(map)?;
 
-        HlAttrDefine { id, hl }
+        // The cterm attributes (index 2) are ignored.
+        let info = match arg(args, 3) {
+            Value::Nil => vec![],
+            info => try_array!(info)
+                .iter()
+                .cloned()
+                .map(HlInfo::try_from)
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(HlAttrDefine { id, hl, info })
     }
 }
 
@@ -584,13 +727,15 @@ pub struct HlGroupSet {
     pub hl_id: u64,
 }
 
-impl From<Value> for HlGroupSet {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let name = unwrap_str!(args[0]).to_string();
-        let hl_id = unwrap_u64!(args[1]);
+impl TryFrom<Value> for HlGroupSet {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let name = try_str!(arg(args, 0)).to_string();
+        let hl_id = try_u64!(arg(args, 1));
 
-        HlGroupSet { name, hl_id }
+        Ok(HlGroupSet { name, hl_id })
     }
 }
 
@@ -600,26 +745,28 @@ pub struct ModeInfoSet {
     pub mode_info: Vec<ModeInfo>,
 }
 
-impl From<Value> for ModeInfoSet {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let cursor_shape_enabled = unwrap_bool!(args[0]);
+impl TryFrom<Value> for ModeInfoSet {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let cursor_shape_enabled = try_bool!(arg(args, 0));
 
         let mut mode_info = vec![];
-        for info in unwrap_array!(args[1]).iter() {
-            let map = unwrap_map!(info);
+        for info in try_array!(arg(args, 1)).iter() {
+            let map = try_map!(info);
 
             let mut mode = ModeInfo::default();
             for (prop, val) in map {
-                mode.set(unwrap_str!(prop), val.clone());
+                mode.set(try_str!(prop), val.clone())?;
             }
             mode_info.push(mode);
         }
 
-        ModeInfoSet {
+        Ok(ModeInfoSet {
             cursor_shape_enabled,
             mode_info,
-        }
+        })
     }
 }
 
@@ -629,13 +776,15 @@ pub struct ModeChange {
     pub index: u64,
 }
 
-impl From<Value> for ModeChange {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let name = unwrap_str!(args[0]).to_string();
-        let index = unwrap_u64!(args[1]);
+impl TryFrom<Value> for ModeChange {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let name = try_str!(arg(args, 0)).to_string();
+        let index = try_u64!(arg(args, 1));
 
-        ModeChange { name, index }
+        Ok(ModeChange { name, index })
     }
 }
 
@@ -645,13 +794,15 @@ pub struct CmdlinePos {
     pub level: u64,
 }
 
-impl From<Value> for CmdlinePos {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let pos = unwrap_u64!(args[0]);
-        let level = unwrap_u64!(args[1]);
+impl TryFrom<Value> for CmdlinePos {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let pos = try_u64!(arg(args, 0));
+        let level = try_u64!(arg(args, 1));
 
-        CmdlinePos { pos, level }
+        Ok(CmdlinePos { pos, level })
     }
 }
 
@@ -662,18 +813,20 @@ pub struct CmdlineSpecialChar {
     pub level: u64,
 }
 
-impl From<Value> for CmdlineSpecialChar {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        let c = unwrap_str!(args[0]);
-        let shift = unwrap_bool!(args[1]);
-        let level = unwrap_u64!(args[2]);
+impl TryFrom<Value> for CmdlineSpecialChar {
+    type Error = ParseError;
 
-        CmdlineSpecialChar {
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let c = try_str!(arg(args, 0));
+        let shift = try_bool!(arg(args, 1));
+        let level = try_u64!(arg(args, 2));
+
+        Ok(CmdlineSpecialChar {
             character: c.to_string(),
             shift,
             level,
-        }
+        })
     }
 }
 
@@ -682,19 +835,13 @@ pub struct CmdlineBlockAppend {
     pub line: Vec<(u64, String)>,
 }
 
-impl From<Value> for CmdlineBlockAppend {
-    fn from(args: Value) -> Self {
-        let line = unwrap_array!(args[0])
-            .iter()
-            .map(|v| {
-                let hl_id = unwrap_u64!(v[0]);
-                let text = unwrap_str!(v[1]);
+impl TryFrom<Value> for CmdlineBlockAppend {
+    type Error = ParseError;
 
-                (hl_id, String::from(text))
-            })
-            .collect();
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let line = parse_hl_chunks(arg(try_array!(args), 0))?;
 
-        Self { line }
+        Ok(Self { line })
     }
 }
 
@@ -704,21 +851,23 @@ pub struct TablineUpdate {
     pub tabs: Vec<(Value, String)>,
 }
 
-impl From<Value> for TablineUpdate {
-    fn from(args: Value) -> Self {
-        let current = args[0].clone();
-        let tabs = unwrap_array!(args[1])
+impl TryFrom<Value> for TablineUpdate {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        let current = arg(args, 0).clone();
+        let tabs = try_array!(arg(args, 1))
             .iter()
-            .map(|item| {
-                let m = map_to_hash(&item);
-                (
-                    (*m.get("tab").unwrap()).clone(),
-                    unwrap_str!(m.get("name").unwrap()).to_string(),
-                )
+            .map(|item| -> Result<_, ParseError> {
+                let m = map_to_hash(&item)?;
+                let tab = m.get("tab").ok_or("tab missing")?;
+                let name = m.get("name").ok_or("name missing")?;
+                Ok(((*tab).clone(), try_str!(name).to_string()))
             })
-            .collect();
+            .collect::<Result<_, _>>()?;
 
-        Self { current, tabs }
+        Ok(Self { current, tabs })
     }
 }
 
@@ -727,24 +876,16 @@ pub struct CmdlineBlockShow {
     pub lines: Vec<Vec<(u64, String)>>,
 }
 
-impl From<Value> for CmdlineBlockShow {
-    fn from(args: Value) -> Self {
-        let lines = unwrap_array!(args)
-            .iter()
-            .map(|line| {
-                unwrap_array!(line[0])
-                    .iter()
-                    .map(|v| {
-                        let hl_id = unwrap_u64!(v[0]);
-                        let text = unwrap_str!(v[1]);
+impl TryFrom<Value> for CmdlineBlockShow {
+    type Error = ParseError;
 
-                        (hl_id, String::from(text))
-                    })
-                    .collect()
-            })
-            .collect();
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let lines = try_array!(args)
+            .iter()
+            .map(|line| parse_hl_chunks(arg(try_array!(line), 0)))
+            .collect::<Result<_, _>>()?;
 
-        Self { lines }
+        Ok(Self { lines })
     }
 }
 
@@ -758,17 +899,19 @@ pub struct WindowPos {
     pub height: u64,
 }
 
-impl From<Value> for WindowPos {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        Self {
-            grid: unwrap_i64!(args[0]),
-            win: args[1].clone(),
-            start_row: unwrap_u64!(args[2]),
-            start_col: unwrap_u64!(args[3]),
-            width: unwrap_u64!(args[4]),
-            height: unwrap_u64!(args[5]),
-        }
+impl TryFrom<Value> for WindowPos {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(Self {
+            grid: try_i64!(arg(args, 0)),
+            win: arg(args, 1).clone(),
+            start_row: try_u64!(arg(args, 2)),
+            start_col: try_u64!(arg(args, 3)),
+            width: try_u64!(arg(args, 4)),
+            height: try_u64!(arg(args, 5)),
+        })
     }
 }
 
@@ -796,16 +939,18 @@ impl Anchor {
     }
 }
 
-impl From<Value> for Anchor {
-    fn from(args: Value) -> Self {
-        let args = unwrap_str!(args);
-        match args {
+impl TryFrom<Value> for Anchor {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_str!(args);
+        Ok(match args {
             "NW" => Self::NW,
             "NE" => Self::NE,
             "SW" => Self::SW,
             "SE" => Self::SE,
             _ => Self::NW,
-        }
+        })
     }
 }
 
@@ -820,18 +965,20 @@ pub struct WindowFloatPos {
     pub focusable: bool,
 }
 
-impl From<Value> for WindowFloatPos {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        Self {
-            grid: unwrap_i64!(args[0]),
-            win: args[1].clone(),
-            anchor: Anchor::from(args[2].clone()),
-            anchor_grid: unwrap_i64!(args[3]),
-            anchor_row: unwrap_f64!(args[4]),
-            anchor_col: unwrap_f64!(args[5]),
-            focusable: unwrap_bool!(args[6]),
-        }
+impl TryFrom<Value> for WindowFloatPos {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(Self {
+            grid: try_i64!(arg(args, 0)),
+            win: arg(args, 1).clone(),
+            anchor: Anchor::try_from(arg(args, 2).clone())?,
+            anchor_grid: try_i64!(arg(args, 3)),
+            anchor_row: try_f64!(arg(args, 4)),
+            anchor_col: try_f64!(arg(args, 5)),
+            focusable: try_bool!(arg(args, 6)),
+        })
     }
 }
 
@@ -841,13 +988,15 @@ pub struct WindowExternalPos {
     pub win: Value,
 }
 
-impl From<Value> for WindowExternalPos {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        Self {
-            grid: unwrap_i64!(args[0]),
-            win: args[1].clone(),
-        }
+impl TryFrom<Value> for WindowExternalPos {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(Self {
+            grid: try_i64!(arg(args, 0)),
+            win: arg(args, 1).clone(),
+        })
     }
 }
 
@@ -859,21 +1008,103 @@ pub struct MsgSetPos {
     pub sep_char: String,
 }
 
-impl From<Value> for MsgSetPos {
-    fn from(args: Value) -> Self {
-        let args = unwrap_array!(args);
-        Self {
-            grid: unwrap_i64!(args[0]),
-            row: unwrap_u64!(args[1]),
-            scrolled: unwrap_bool!(args[2]),
-            sep_char: unwrap_str!(args[3]).to_string(),
-        }
+impl TryFrom<Value> for MsgSetPos {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(Self {
+            grid: try_i64!(arg(args, 0)),
+            row: try_u64!(arg(args, 1)),
+            scrolled: try_bool!(arg(args, 2)),
+            sep_char: try_str!(arg(args, 3)).to_string(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WinViewport {
+    pub grid: i64,
+    pub win: Value,
+    /// First line that is shown (0-based).
+    pub topline: u64,
+    /// Line after the last line that is shown.
+    pub botline: u64,
+    pub curline: u64,
+    pub curcol: u64,
+    /// Number of lines in the buffer. Older nvims don't send this.
+    pub line_count: Option<u64>,
+}
+
+impl TryFrom<Value> for WinViewport {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(Self {
+            grid: try_i64!(arg(args, 0)),
+            win: arg(args, 1).clone(),
+            topline: try_u64!(arg(args, 2)),
+            botline: try_u64!(arg(args, 3)),
+            curline: try_u64!(arg(args, 4)),
+            curcol: try_u64!(arg(args, 5)),
+            line_count: match arg(args, 6) {
+                Value::Nil => None,
+                val => Some(try_u64!(val)),
+            },
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MsgShow {
+    /// Kind of the message (e.g. `emsg`). Might be empty.
+    pub kind: String,
+    pub content: Vec<(u64, String)>,
+    /// If the message should replace the previous one.
+    pub replace_last: bool,
+}
+
+impl TryFrom<Value> for MsgShow {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let args = try_array!(args);
+        Ok(Self {
+            kind: try_str!(arg(args, 0)).to_string(),
+            content: parse_hl_chunks(arg(args, 1))?,
+            replace_last: try_bool!(arg(args, 2)),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MsgHistoryShow {
+    /// Kind and content of each message in the history.
+    pub entries: Vec<(String, Vec<(u64, String)>)>,
+}
+
+impl TryFrom<Value> for MsgHistoryShow {
+    type Error = ParseError;
+
+    fn try_from(args: Value) -> Result<Self, Self::Error> {
+        let entries = try_array!(arg(try_array!(args), 0))
+            .iter()
+            .map(|entry| -> Result<_, ParseError> {
+                let entry = try_array!(entry);
+                let kind = try_str!(arg(entry, 0)).to_string();
+                Ok((kind, parse_hl_chunks(arg(entry, 1))?))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { entries })
     }
 }
 
 #[derive(Debug, PartialEq)]
 pub enum RedrawEvent {
     SetTitle(Vec<String>),
+    Chdir(Vec<String>),
 
     GridLine(Vec<GridLineSegment>),
     GridResize(Vec<GridResize>),
@@ -911,15 +1142,36 @@ pub enum RedrawEvent {
     WindowHide(Vec<i64>),
     WindowClose(Vec<i64>),
     MsgSetPos(Vec<MsgSetPos>),
+    WinViewport(Vec<WinViewport>),
+
+    MouseOn(),
+    MouseOff(),
+
+    Bell(),
+    VisualBell(),
+
+    MsgShow(Vec<MsgShow>),
+    MsgClear(),
+    /// Content of each `msg_showmode` event.
+    MsgShowmode(Vec<Vec<(u64, String)>>),
+    /// Content of each `msg_showcmd` event.
+    MsgShowcmd(Vec<Vec<(u64, String)>>),
+    /// Content of each `msg_ruler` event.
+    MsgRuler(Vec<Vec<(u64, String)>>),
+    MsgHistoryShow(Vec<MsgHistoryShow>),
+    MsgHistoryClear(),
 
     Ignored(String),
     Unknown(String),
+    /// Event (name) that we failed to parse.
+    Malformed(String, ParseError),
 }
 
 impl fmt::Display for RedrawEvent {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             RedrawEvent::SetTitle(..) => write!(fmt, "SetTitle"),
+            RedrawEvent::Chdir(..) => write!(fmt, "Chdir"),
             RedrawEvent::GridLine(..) => write!(fmt, "GridLine"),
             RedrawEvent::GridResize(..) => write!(fmt, "GridResize"),
             RedrawEvent::GridCursorGoto(..) => write!(fmt, "GridCursorGoto"),
@@ -964,9 +1216,22 @@ impl fmt::Display for RedrawEvent {
             RedrawEvent::WindowHide(..) => write!(fmt, "WindowHide"),
             RedrawEvent::WindowClose(..) => write!(fmt, "WindowClose"),
             RedrawEvent::MsgSetPos(..) => write!(fmt, "MsgSetPos"),
+            RedrawEvent::WinViewport(..) => write!(fmt, "WinViewport"),
+            RedrawEvent::MouseOn(..) => write!(fmt, "MouseOn"),
+            RedrawEvent::MouseOff(..) => write!(fmt, "MouseOff"),
+            RedrawEvent::Bell(..) => write!(fmt, "Bell"),
+            RedrawEvent::VisualBell(..) => write!(fmt, "VisualBell"),
+            RedrawEvent::MsgShow(..) => write!(fmt, "MsgShow"),
+            RedrawEvent::MsgClear(..) => write!(fmt, "MsgClear"),
+            RedrawEvent::MsgShowmode(..) => write!(fmt, "MsgShowmode"),
+            RedrawEvent::MsgShowcmd(..) => write!(fmt, "MsgShowcmd"),
+            RedrawEvent::MsgRuler(..) => write!(fmt, "MsgRuler"),
+            RedrawEvent::MsgHistoryShow(..) => write!(fmt, "MsgHistoryShow"),
+            RedrawEvent::MsgHistoryClear(..) => write!(fmt, "MsgHistoryClear"),
 
             RedrawEvent::Ignored(..) => write!(fmt, "Ignored"),
             RedrawEvent::Unknown(e) => write!(fmt, "Unknown({})", e),
+            RedrawEvent::Malformed(e, _) => write!(fmt, "Malformed({})", e),
         }
     }
 }
@@ -989,12 +1254,154 @@ pub enum GnvimEvent {
     EnableExtTabline(bool),
     EnableExtCmdline(bool),
     EnableExtPopupmenu(bool),
+    EnableExtMessages(bool),
+    EnableExtMultigrid(bool),
+
+    /// Open a new gnvim window (with its own nvim).
+    NewWindow,
+    /// Let the user choose files to open with a file chooser.
+    Open,
+
+    /// Nvim's current directory changed (`DirChanged`).
+    DirChanged(String),
 
     Unknown(String),
 }
 
 pub enum Request {
     CursorTooltipStyles,
+    /// Version of our api and what it supports (see `api_info`).
+    ApiInfo,
+    /// Names of the highlight groups of the cell under the cursor.
+    CursorHlNames,
+}
+
+/// Version of the `Gnvim` rpc api. Bumped when existing events or requests
+/// change in an incompatible way.
+pub const API_VERSION: u64 = 1;
+
+/// Optional feature for the cursor tooltip (needs `libwebkit2gtk`).
+const FEATURE_CURSOR_TOOLTIP: &str = "cursor_tooltip";
+
+/// Names of the `GnvimEvent`s, with the feature they need (if any).
+const GNVIM_EVENTS: &[(&str, Option<&str>)] = &[
+    ("CompletionMenuToggleInfo", None),
+    ("CursorTooltipLoadStyle", Some(FEATURE_CURSOR_TOOLTIP)),
+    ("CursorTooltipShow", Some(FEATURE_CURSOR_TOOLTIP)),
+    ("CursorTooltipHide", Some(FEATURE_CURSOR_TOOLTIP)),
+    ("CursorTooltipSetStyle", Some(FEATURE_CURSOR_TOOLTIP)),
+    ("PopupmenuSetWidth", None),
+    ("PopupmenuSetWidthDetails", None),
+    ("PopupmenuShowMenuOnAllItems", None),
+    ("EnableCursorAnimations", None),
+    ("EnableExtTabline", None),
+    ("EnableExtCmdline", None),
+    ("EnableExtPopupmenu", None),
+    ("EnableExtMessages", None),
+    ("EnableExtMultigrid", None),
+    ("NewWindow", None),
+    ("Open", None),
+    ("DirChanged", None),
+];
+
+/// Names of the `Request`s, with the feature they need (if any).
+const REQUESTS: &[(&str, Option<&str>)] = &[
+    ("CursorTooltipGetStyles", Some(FEATURE_CURSOR_TOOLTIP)),
+    ("ApiInfo", None),
+    ("CursorHlNames", None),
+];
+
+/// Optional features that this build of gnvim has.
+fn features() -> Vec<&'static str> {
+    let mut features = vec![];
+    if cfg!(feature = "libwebkit2gtk") {
+        features.push(FEATURE_CURSOR_TOOLTIP);
+    }
+
+    features
+}
+
+/// Returns the names from `list` whose feature is in `features`.
+fn supported(list: &[(&str, Option<&str>)], features: &[&str]) -> Value {
+    list.iter()
+        .filter(|(_, feature)| {
+            feature
+                .map_or(true, |feature| features.iter().any(|f| *f == feature))
+        })
+        .map(|(name, _)| Value::from(*name))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Response to `Request::ApiInfo`. A map with the api `version`, and lists
+/// of the supported `events`, `requests` and `features`.
+pub fn api_info() -> Value {
+    let features = features();
+
+    Value::Map(vec![
+        ("version".into(), API_VERSION.into()),
+        ("events".into(), supported(GNVIM_EVENTS, &features)),
+        ("requests".into(), supported(REQUESTS, &features)),
+        (
+            "features".into(),
+            features
+                .iter()
+                .map(|feature| Value::from(*feature))
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+    ])
+}
+
+/// What nvim tells about itself in the metadata of `nvim_get_api_info`.
+#[derive(Debug, PartialEq)]
+pub struct NvimInfo {
+    /// Nvim's version, e.g. `0.5.0`.
+    pub version: String,
+    pub api_level: u64,
+    /// Options that nvim supports for `nvim_ui_attach` (e.g. `ext_linegrid`).
+    pub ui_options: Vec<String>,
+}
+
+impl NvimInfo {
+    pub fn supports_ui_option(&self, name: &str) -> bool {
+        self.ui_options.iter().any(|opt| opt == name)
+    }
+}
+
+impl TryFrom<Value> for NvimInfo {
+    type Error = ParseError;
+
+    fn try_from(metadata: Value) -> Result<Self, Self::Error> {
+        let metadata = map_to_hash(&metadata)?;
+        let version =
+            map_to_hash(metadata.get("version").ok_or("version missing")?)?;
+        let num = |key: &str| -> Result<u64, ParseError> {
+            Ok(try_u64!(version
+                .get(key)
+                .ok_or("version number missing")?))
+        };
+
+        Ok(Self {
+            version: format!(
+                "{}.{}.{}",
+                num("major")?,
+                num("minor")?,
+                num("patch")?
+            ),
+            api_level: num("api_level")?,
+            // Very old nvims don't tell their ui options.
+            ui_options: match metadata.get("ui_options") {
+                Some(opts) => try_array!(opts)
+                    .iter()
+                    .map(|opt| -> Result<_, ParseError> {
+                        Ok(try_str!(opt).to_string())
+                    })
+                    .collect::<Result<_, _>>()?,
+                None => vec![],
+            },
+        })
+    }
 }
 
 /// Message type that we are sending to the UI.
@@ -1004,7 +1411,17 @@ pub enum Message {
     /// RPC Request (see `: rpcrequest()`).
     Request(Sender<Result<Value, Value>>, Request),
     /// Nvim went away or reading from the rcp connection failed.
-    Close,
+    Close(CloseInfo),
+}
+
+/// Describes why the rpc connection to nvim was closed.
+#[derive(Debug)]
+pub struct CloseInfo {
+    /// What happened (e.g. nvim's exit status).
+    pub reason: String,
+    /// Set if nvim didn't exit cleanly. Contains what nvim wrote to its
+    /// stderr.
+    pub stderr: Option<String>,
 }
 
 #[derive(Clone)]
@@ -1018,6 +1435,9 @@ pub struct NvimBridge {
     request_tx: Arc<ThreadGuard<Sender<Result<Value, Value>>>>,
     /// Receiving end of `request_tx`.
     request_rx: Arc<ThreadGuard<Receiver<Result<Value, Value>>>>,
+
+    /// Writes the notifies we receive to a file, if set.
+    recorder: Option<Arc<Recorder>>,
 }
 
 impl NvimBridge {
@@ -1028,8 +1448,15 @@ impl NvimBridge {
             tx: Arc::new(ThreadGuard::new(tx)),
             request_tx: Arc::new(ThreadGuard::new(request_tx)),
             request_rx: Arc::new(ThreadGuard::new(request_rx)),
+            recorder: None,
         }
     }
+
+    /// Records all the notifies to `recorder`.
+    pub fn with_recorder(mut self, recorder: Arc<Recorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
 }
 
 #[async_trait]
@@ -1046,13 +1473,18 @@ impl Handler for NvimBridge {
             "Gnvim" => match parse_request(args) {
                 Ok(msg) => {
                     let tx = self.tx.borrow_mut();
-                    tx.send(Message::Request(
-                        self.request_tx.borrow_mut().clone(),
-                        msg,
-                    ))
-                    .unwrap();
+                    if tx
+                        .send(Message::Request(
+                            self.request_tx.borrow_mut().clone(),
+                            msg,
+                        ))
+                        .is_err()
+                    {
+                        // The window showing this session is gone.
+                        return Err("UI is gone".into());
+                    }
                     let rx = self.request_rx.borrow_mut();
-                    rx.recv().unwrap()
+                    rx.recv().unwrap_or_else(|_| Err("UI is gone".into()))
                 }
                 Err(_) => Err("Failed to parse request".into()),
             },
@@ -1069,9 +1501,17 @@ impl Handler for NvimBridge {
         args: Vec<Value>,
         _neovim: Neovim<<Self as Handler>::Writer>,
     ) {
+        if let Some(ref recorder) = self.recorder {
+            if let Err(err) = recorder.record(&name, &args) {
+                error!("Failed to record notify: {}", err);
+            }
+        }
+
         if let Some(notify) = parse_notify(&name, args) {
             let tx = self.tx.borrow_mut();
-            tx.send(Message::Notify(notify)).unwrap();
+            if tx.send(Message::Notify(notify)).is_err() {
+                debug!("Notify after the UI is gone: {}", name);
+            }
         } else {
             error!("Unknown notify: {}", name);
         }
@@ -1092,15 +1532,17 @@ impl Spawner for NvimBridge {
 }
 
 fn parse_request(args: Vec<Value>) -> Result<Request, ()> {
-    let cmd = unwrap_str!(args[0]);
+    let cmd = arg(&args, 0).as_str().ok_or(())?;
 
     match cmd {
         "CursorTooltipGetStyles" => Ok(Request::CursorTooltipStyles),
+        "ApiInfo" => Ok(Request::ApiInfo),
+        "CursorHlNames" => Ok(Request::CursorHlNames),
         _ => Err(()),
     }
 }
 
-fn parse_notify(name: &str, args: Vec<Value>) -> Option<Notify> {
+pub(crate) fn parse_notify(name: &str, args: Vec<Value>) -> Option<Notify> {
     match name {
         "redraw" => Some(Notify::RedrawEvent(parse_redraw_event(args))),
         "Gnvim" => Some(Notify::GnvimEvent(parse_gnvim_event(args))),
@@ -1108,119 +1550,177 @@ fn parse_notify(name: &str, args: Vec<Value>) -> Option<Notify> {
     }
 }
 
-fn parse_single_redraw_event(cmd: &str, args: Vec<Value>) -> RedrawEvent {
-    match cmd {
-        "set_title" => RedrawEvent::SetTitle(
-            args.into_iter()
-                .map(|v| unwrap_str!(v[0]).to_string())
-                .collect(),
-        ),
-        "grid_resize" => RedrawEvent::GridResize(
-            args.into_iter().map(GridResize::from).collect(),
-        ),
-        "grid_cursor_goto" => RedrawEvent::GridCursorGoto(
-            args.into_iter().map(GridCursorGoto::from).collect(),
-        ),
-        "grid_clear" => RedrawEvent::GridClear(
-            args.into_iter().map(|v| unwrap_i64!(v[0])).collect(),
-        ),
-        "grid_destroy" => RedrawEvent::GridDestroy(
-            args.into_iter().map(|v| unwrap_i64!(v[0])).collect(),
-        ),
-        "grid_scroll" => RedrawEvent::GridScroll(
-            args.into_iter().map(GridScroll::from).collect(),
-        ),
-        "grid_line" => RedrawEvent::GridLine(
-            args.into_iter().map(GridLineSegment::from).collect(),
-        ),
-        "default_colors_set" => RedrawEvent::DefaultColorsSet(
-            args.into_iter().map(DefaultColorsSet::from).collect(),
-        ),
-        "hl_attr_define" => RedrawEvent::HlAttrDefine(
-            args.into_iter().map(HlAttrDefine::from).collect(),
-        ),
-        "hl_group_set" => RedrawEvent::HlGroupSet(
-            args.into_iter().map(HlGroupSet::from).collect(),
-        ),
-        "option_set" => RedrawEvent::OptionSet(
-            args.into_iter().map(OptionSet::from).collect(),
-        ),
-        "mode_info_set" => RedrawEvent::ModeInfoSet(
-            args.into_iter().map(ModeInfoSet::from).collect(),
-        ),
-        "mode_change" => RedrawEvent::ModeChange(
-            args.into_iter().map(ModeChange::from).collect(),
-        ),
+fn parse_single_redraw_event(
+    cmd: &str,
+    args: Vec<Value>,
+) -> Result<RedrawEvent, ParseError> {
+    Ok(match cmd {
+        "set_title" => RedrawEvent::SetTitle(parse_args(args, |v| {
+            Ok(try_str!(arg(try_array!(v), 0)).to_string())
+        })?),
+        "chdir" => RedrawEvent::Chdir(parse_args(args, |v| {
+            Ok(try_str!(arg(try_array!(v), 0)).to_string())
+        })?),
+        "grid_resize" => {
+            RedrawEvent::GridResize(parse_args(args, GridResize::try_from)?)
+        }
+        "grid_cursor_goto" => RedrawEvent::GridCursorGoto(parse_args(
+            args,
+            GridCursorGoto::try_from,
+        )?),
+        "grid_clear" => RedrawEvent::GridClear(parse_args(args, |v| {
+            Ok(try_i64!(arg(try_array!(v), 0)))
+        })?),
+        "grid_destroy" => RedrawEvent::GridDestroy(parse_args(args, |v| {
+            Ok(try_i64!(arg(try_array!(v), 0)))
+        })?),
+        "grid_scroll" => {
+            RedrawEvent::GridScroll(parse_args(args, GridScroll::try_from)?)
+        }
+        "grid_line" => {
+            RedrawEvent::GridLine(parse_args(args, GridLineSegment::try_from)?)
+        }
+        "default_colors_set" => RedrawEvent::DefaultColorsSet(parse_args(
+            args,
+            DefaultColorsSet::try_from,
+        )?),
+        "hl_attr_define" => {
+            RedrawEvent::HlAttrDefine(parse_args(args, HlAttrDefine::try_from)?)
+        }
+        "hl_group_set" => {
+            RedrawEvent::HlGroupSet(parse_args(args, HlGroupSet::try_from)?)
+        }
+        "option_set" => {
+            RedrawEvent::OptionSet(parse_args(args, OptionSet::try_from)?)
+        }
+        "mode_info_set" => {
+            RedrawEvent::ModeInfoSet(parse_args(args, ModeInfoSet::try_from)?)
+        }
+        "mode_change" => {
+            RedrawEvent::ModeChange(parse_args(args, ModeChange::try_from)?)
+        }
         "busy_start" => RedrawEvent::SetBusy(true),
         "busy_stop" => RedrawEvent::SetBusy(false),
         "flush" => RedrawEvent::Flush(),
-        "popupmenu_show" => RedrawEvent::PopupmenuShow(
-            args.into_iter().map(PopupmenuShow::from).collect(),
-        ),
+        "popupmenu_show" => RedrawEvent::PopupmenuShow(parse_args(
+            args,
+            PopupmenuShow::try_from,
+        )?),
         "popupmenu_hide" => RedrawEvent::PopupmenuHide(),
-        "popupmenu_select" => RedrawEvent::PopupmenuSelect(
-            args.into_iter().map(|s| unwrap_i64!(s[0])).collect(),
-        ),
-        "tabline_update" => RedrawEvent::TablineUpdate(
-            args.into_iter().map(TablineUpdate::from).collect(),
-        ),
-        "cmdline_show" => RedrawEvent::CmdlineShow(
-            args.into_iter().map(CmdlineShow::from).collect(),
-        ),
+        "popupmenu_select" => {
+            RedrawEvent::PopupmenuSelect(parse_args(args, |v| {
+                Ok(try_i64!(arg(try_array!(v), 0)))
+            })?)
+        }
+        "tabline_update" => RedrawEvent::TablineUpdate(parse_args(
+            args,
+            TablineUpdate::try_from,
+        )?),
+        "cmdline_show" => {
+            RedrawEvent::CmdlineShow(parse_args(args, CmdlineShow::try_from)?)
+        }
         "cmdline_hide" => RedrawEvent::CmdlineHide(),
-        "cmdline_pos" => RedrawEvent::CmdlinePos(
-            args.into_iter().map(CmdlinePos::from).collect(),
-        ),
-        "cmdline_special_char" => RedrawEvent::CmdlineSpecialChar(
-            args.into_iter().map(CmdlineSpecialChar::from).collect(),
-        ),
-        "cmdline_block_show" => RedrawEvent::CmdlineBlockShow(
-            args.into_iter().map(CmdlineBlockShow::from).collect(),
-        ),
-        "cmdline_block_append" => RedrawEvent::CmdlineBlockAppend(
-            args.into_iter().map(CmdlineBlockAppend::from).collect(),
-        ),
+        "cmdline_pos" => {
+            RedrawEvent::CmdlinePos(parse_args(args, CmdlinePos::try_from)?)
+        }
+        "cmdline_special_char" => RedrawEvent::CmdlineSpecialChar(parse_args(
+            args,
+            CmdlineSpecialChar::try_from,
+        )?),
+        "cmdline_block_show" => RedrawEvent::CmdlineBlockShow(parse_args(
+            args,
+            CmdlineBlockShow::try_from,
+        )?),
+        "cmdline_block_append" => RedrawEvent::CmdlineBlockAppend(parse_args(
+            args,
+            CmdlineBlockAppend::try_from,
+        )?),
         "cmdline_block_hide" => RedrawEvent::CmdlineBlockHide(),
-        "win_pos" => RedrawEvent::WindowPos(
-            args.into_iter().map(WindowPos::from).collect(),
-        ),
-        "win_float_pos" => RedrawEvent::WindowFloatPos(
-            args.into_iter().map(WindowFloatPos::from).collect(),
-        ),
-        "win_external_pos" => RedrawEvent::WindowExternalPos(
-            args.into_iter().map(WindowExternalPos::from).collect(),
-        ),
-        "win_hide" => RedrawEvent::WindowHide(
-            args.into_iter()
-                .map(|v| {
-                    let v = unwrap_array!(v);
-                    unwrap_i64!(v[0])
-                })
-                .collect(),
-        ),
-        "win_close" => RedrawEvent::WindowClose(
-            args.into_iter()
-                .map(|v| {
-                    let v = unwrap_array!(v);
-                    unwrap_i64!(v[0])
-                })
-                .collect(),
-        ),
-        "msg_set_pos" => RedrawEvent::MsgSetPos(
-            args.into_iter().map(MsgSetPos::from).collect(),
-        ),
-
-        "mouse_on" | "mouse_off" => RedrawEvent::Ignored(cmd.to_string()),
+        "win_pos" => {
+            RedrawEvent::WindowPos(parse_args(args, WindowPos::try_from)?)
+        }
+        "win_float_pos" => RedrawEvent::WindowFloatPos(parse_args(
+            args,
+            WindowFloatPos::try_from,
+        )?),
+        "win_external_pos" => RedrawEvent::WindowExternalPos(parse_args(
+            args,
+            WindowExternalPos::try_from,
+        )?),
+        "win_hide" => RedrawEvent::WindowHide(parse_args(args, |v| {
+            Ok(try_i64!(arg(try_array!(v), 0)))
+        })?),
+        "win_close" => RedrawEvent::WindowClose(parse_args(args, |v| {
+            Ok(try_i64!(arg(try_array!(v), 0)))
+        })?),
+        "msg_set_pos" => {
+            RedrawEvent::MsgSetPos(parse_args(args, MsgSetPos::try_from)?)
+        }
+        "win_viewport" => {
+            RedrawEvent::WinViewport(parse_args(args, WinViewport::try_from)?)
+        }
+        "msg_show" => {
+            RedrawEvent::MsgShow(parse_args(args, MsgShow::try_from)?)
+        }
+        "msg_clear" => RedrawEvent::MsgClear(),
+        "msg_showmode" => RedrawEvent::MsgShowmode(parse_args(args, |v| {
+            parse_hl_chunks(arg(try_array!(v), 0))
+        })?),
+        "msg_showcmd" => RedrawEvent::MsgShowcmd(parse_args(args, |v| {
+            parse_hl_chunks(arg(try_array!(v), 0))
+        })?),
+        "msg_ruler" => RedrawEvent::MsgRuler(parse_args(args, |v| {
+            parse_hl_chunks(arg(try_array!(v), 0))
+        })?),
+        "msg_history_show" => RedrawEvent::MsgHistoryShow(parse_args(
+            args,
+            MsgHistoryShow::try_from,
+        )?),
+        "msg_history_clear" => RedrawEvent::MsgHistoryClear(),
+
+        "mouse_on" => RedrawEvent::MouseOn(),
+        "mouse_off" => RedrawEvent::MouseOff(),
+        "bell" => RedrawEvent::Bell(),
+        "visual_bell" => RedrawEvent::VisualBell(),
         _ => RedrawEvent::Unknown(cmd.to_string()),
-    }
+    })
 }
 
+/// Parses each of the `args` of a single redraw event with `f`.
+fn parse_args<T, F>(args: Vec<Value>, f: F) -> Result<Vec<T>, ParseError>
+where
+    F: Fn(Value) -> Result<T, ParseError>,
+{
+    args.into_iter().map(f).collect()
+}
+
+/// Parses the redraw events of a single redraw notify. Events that we fail
+/// to parse are turned into `RedrawEvent::Malformed`.
 pub(crate) fn parse_redraw_event(args: Vec<Value>) -> Vec<RedrawEvent> {
     args.into_iter()
         .map(|args| {
-            let args = unwrap_array!(args);
-            let cmd = unwrap_str!(args[0]);
-            parse_single_redraw_event(cmd, args[1..].to_vec())
+            let (cmd, args) = match args {
+                Value::Array(mut args) if !args.is_empty() => {
+                    let cmd = args.remove(0);
+                    (cmd, args)
+                }
+                args => {
+                    let err = ParseError::invalid("event", &args);
+                    return RedrawEvent::Malformed(String::new(), err);
+                }
+            };
+
+            let cmd = match cmd.as_str() {
+                Some(cmd) => cmd,
+                None => {
+                    let err = ParseError::invalid("event name", &cmd);
+                    return RedrawEvent::Malformed(String::new(), err);
+                }
+            };
+
+            parse_single_redraw_event(cmd, args).unwrap_or_else(|err| {
+                RedrawEvent::Malformed(cmd.to_string(), err)
+            })
         })
         .collect()
 }
@@ -1297,17 +1797,51 @@ pub(crate) fn parse_gnvim_event(
                 "failed to parse enable ext popupmenu argument"
             ) == 1,
         ),
+        "EnableExtMessages" => GnvimEvent::EnableExtMessages(
+            try_u64!(
+                args.get(1).ok_or("argument missing")?,
+                "failed to parse enable ext messages argument"
+            ) == 1,
+        ),
+        "EnableExtMultigrid" => GnvimEvent::EnableExtMultigrid(
+            try_u64!(
+                args.get(1).ok_or("argument missing")?,
+                "failed to parse enable ext multigrid argument"
+            ) == 1,
+        ),
+        "NewWindow" => GnvimEvent::NewWindow,
+        "Open" => GnvimEvent::Open,
+        "DirChanged" => {
+            let cwd = try_str!(args.get(1).ok_or("cwd missing")?, "cwd");
+            GnvimEvent::DirChanged(cwd.to_string())
+        }
         _ => GnvimEvent::Unknown(String::from(cmd)),
     };
 
     Ok(res)
 }
 
-fn map_to_hash<'a>(val: &'a Value) -> HashMap<&'a str, &'a Value> {
+/// Parses a list of `[hl_id, text]` chunks.
+fn parse_hl_chunks(val: &Value) -> Result<Vec<(u64, String)>, ParseError> {
+    try_array!(val)
+        .iter()
+        .map(|v| {
+            let v = try_array!(v);
+            let hl_id = try_u64!(arg(v, 0));
+            let text = try_str!(arg(v, 1));
+
+            Ok((hl_id, String::from(text)))
+        })
+        .collect()
+}
+
+fn map_to_hash<'a>(
+    val: &'a Value,
+) -> Result<HashMap<&'a str, &'a Value>, ParseError> {
     let mut h = HashMap::new();
-    for (prop, val) in unwrap_map!(val) {
-        h.insert(unwrap_str!(prop), val);
+    for (prop, val) in try_map!(val) {
+        h.insert(try_str!(prop), val);
     }
 
-    h
+    Ok(h)
 }
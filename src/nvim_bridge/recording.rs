@@ -0,0 +1,151 @@
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rmpv::Value;
+
+/// Writes notifies from nvim to a file (see `--record`), so they can be
+/// replayed later on (see `--replay`).
+///
+/// Each notify is written as a msgpack array of
+/// `[milliseconds since start, name, args]`.
+pub struct Recorder {
+    out: Mutex<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Recorder {
+            out: Mutex::new(out),
+            start: Instant::now(),
+        }
+    }
+
+    /// Writes a notify to the recording.
+    pub fn record(&self, name: &str, args: &[Value]) -> io::Result<()> {
+        let time = self.start.elapsed().as_millis() as u64;
+        let entry = Value::Array(vec![
+            time.into(),
+            name.into(),
+            Value::Array(args.to_vec()),
+        ]);
+
+        let mut out = self.out.lock().unwrap();
+        rmpv::encode::write_value(&mut *out, &entry)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        // Flush right away, so we'll have everything up to the point where
+        // (for example) we crashed.
+        out.flush()
+    }
+}
+
+/// Single notify read from a recording.
+#[derive(Debug, PartialEq)]
+pub struct RecordedNotify {
+    /// When the notify was received, relative to the start of the recording.
+    pub time: Duration,
+    pub name: String,
+    pub args: Vec<Value>,
+}
+
+/// Reads all the notifies from a recording made by `Recorder`.
+pub fn read_recording<R: Read>(mut rd: R) -> io::Result<Vec<RecordedNotify>> {
+    let mut notifies = vec![];
+
+    loop {
+        let entry = match rmpv::decode::read_value(&mut rd) {
+            Ok(entry) => entry,
+            Err(rmpv::decode::Error::InvalidMarkerRead(ref err))
+                if err.kind() == io::ErrorKind::UnexpectedEof =>
+            {
+                break
+            }
+            Err(err) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    err.to_string(),
+                ))
+            }
+        };
+
+        notifies.push(parse_entry(entry)?);
+    }
+
+    Ok(notifies)
+}
+
+fn parse_entry(entry: Value) -> io::Result<RecordedNotify> {
+    let invalid = || {
+        io::Error::new(io::ErrorKind::InvalidData, "invalid recording entry")
+    };
+
+    let mut entry = match entry {
+        Value::Array(entry) if entry.len() == 3 => entry.into_iter(),
+        _ => return Err(invalid()),
+    };
+
+    let time = entry.next().and_then(|v| v.as_u64()).ok_or_else(invalid)?;
+    let name = entry
+        .next()
+        .and_then(|v| v.as_str().map(String::from))
+        .ok_or_else(invalid)?;
+    let args = match entry.next() {
+        Some(Value::Array(args)) => args,
+        _ => return Err(invalid()),
+    };
+
+    Ok(RecordedNotify {
+        time: Duration::from_millis(time),
+        name,
+        args,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    /// Writer that we can read back from after the recorder is done with it.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_recording_roundtrip() {
+        let buf = SharedBuf::default();
+        let recorder = Recorder::new(Box::new(buf.clone()));
+
+        let args = vec![Value::Array(vec!["flush".into()])];
+        recorder.record("redraw", &args).unwrap();
+        recorder.record("Gnvim", &["NewWindow".into()]).unwrap();
+
+        let data = buf.0.lock().unwrap().clone();
+        let notifies = read_recording(&data[..]).unwrap();
+
+        assert_eq!(notifies.len(), 2);
+        assert_eq!(notifies[0].name, "redraw");
+        assert_eq!(notifies[0].args, args);
+        assert_eq!(notifies[1].name, "Gnvim");
+        assert!(notifies[0].time <= notifies[1].time);
+    }
+
+    #[test]
+    fn test_read_recording_invalid() {
+        let mut data = vec![];
+        rmpv::encode::write_value(&mut data, &Value::from("foo")).unwrap();
+
+        assert!(read_recording(&data[..]).is_err());
+    }
+}
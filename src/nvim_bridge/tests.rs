@@ -19,8 +19,8 @@ mod parse_redraw_event_tests {
         Cell, CmdlineBlockAppend, CmdlinePos, CmdlineShow, CmdlineSpecialChar,
         CompletionItem, CompletionItemKind, CursorShape, DefaultColorsSet,
         GridCursorGoto, GridLineSegment, GridResize, GridScroll, HlAttrDefine,
-        ModeChange, ModeInfo, ModeInfoSet, OptionSet, PopupmenuShow,
-        RedrawEvent, TablineUpdate,
+        HlInfo, ModeChange, ModeInfo, ModeInfoSet, MsgHistoryShow, MsgShow,
+        OptionSet, PopupmenuShow, RedrawEvent, TablineUpdate, WinViewport,
     };
     use crate::ui::color::{Color, Highlight};
     use rmpv::Value;
@@ -38,6 +38,18 @@ mod parse_redraw_event_tests {
         assert_eq!(expected, res);
     }
 
+    #[test]
+    fn chdir() {
+        let expected = vec![RedrawEvent::Chdir(vec!["/tmp".to_string()])];
+
+        let res = nvim_bridge::parse_redraw_event(args!(
+            "chdir".into(),
+            Value::Array(vec!("/tmp".into()))
+        ));
+
+        assert_eq!(expected, res);
+    }
+
     #[test]
     fn grid_line() {
         let expected = vec![RedrawEvent::GridLine(vec![
@@ -243,8 +255,15 @@ mod parse_redraw_event_tests {
                     bold: true,
                     underline: true,
                     undercurl: false,
+                    underdouble: false,
+                    underdotted: false,
+                    underdashed: false,
+                    strikethrough: false,
+                    altfont: false,
+                    nocombine: false,
                     blend: 0.0,
                 },
+                info: vec![],
             },
             HlAttrDefine {
                 id: 42,
@@ -257,8 +276,15 @@ mod parse_redraw_event_tests {
                     bold: true,
                     underline: false,
                     undercurl: true,
+                    underdouble: false,
+                    underdotted: false,
+                    underdashed: false,
+                    strikethrough: false,
+                    altfont: false,
+                    nocombine: false,
                     blend: 0.0,
                 },
+                info: vec![],
             },
             HlAttrDefine {
                 id: 32,
@@ -271,8 +297,15 @@ mod parse_redraw_event_tests {
                     bold: true,
                     underline: false,
                     undercurl: true,
+                    underdouble: false,
+                    underdotted: false,
+                    underdashed: false,
+                    strikethrough: false,
+                    altfont: false,
+                    nocombine: false,
                     blend: 0.3,
                 },
+                info: vec![],
             },
             HlAttrDefine {
                 id: 3,
@@ -285,8 +318,15 @@ mod parse_redraw_event_tests {
                     bold: false,
                     underline: false,
                     undercurl: false,
+                    underdouble: false,
+                    underdotted: false,
+                    underdashed: false,
+                    strikethrough: false,
+                    altfont: false,
+                    nocombine: false,
                     blend: 0.0,
                 },
+                info: vec![],
             },
         ])];
 
@@ -333,17 +373,140 @@ mod parse_redraw_event_tests {
         assert_eq!(expected, res);
     }
 
+    #[test]
+    fn hl_attr_define_decorations() {
+        let expected = vec![RedrawEvent::HlAttrDefine(vec![
+            HlAttrDefine {
+                id: 1,
+                hl: Highlight {
+                    underdouble: true,
+                    underdotted: true,
+                    underdashed: true,
+                    strikethrough: true,
+                    altfont: true,
+                    nocombine: true,
+                    ..Highlight::default()
+                },
+                info: vec![],
+            },
+            // Pre nvim 0.8 names.
+            HlAttrDefine {
+                id: 2,
+                hl: Highlight {
+                    underdouble: true,
+                    underdotted: true,
+                    underdashed: true,
+                    ..Highlight::default()
+                },
+                info: vec![],
+            },
+        ])];
+
+        let res = nvim_bridge::parse_redraw_event(args!(
+            "hl_attr_define".into(),
+            Value::Array(vec!(
+                1.into(),
+                Value::Map(vec!(
+                    ("underdouble".into(), true.into()),
+                    ("underdotted".into(), true.into()),
+                    ("underdashed".into(), true.into()),
+                    ("strikethrough".into(), true.into()),
+                    ("altfont".into(), true.into()),
+                    ("nocombine".into(), true.into()),
+                )),
+            )),
+            Value::Array(vec!(
+                2.into(),
+                Value::Map(vec!(
+                    ("underlineline".into(), true.into()),
+                    ("underdot".into(), true.into()),
+                    ("underdash".into(), true.into()),
+                )),
+            ))
+        ));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn hl_attr_define_with_info() {
+        let expected = vec![RedrawEvent::HlAttrDefine(vec![HlAttrDefine {
+            id: 7,
+            hl: Highlight {
+                bold: true,
+                ..Highlight::default()
+            },
+            info: vec![
+                HlInfo {
+                    kind: "ui".to_string(),
+                    ui_name: Some("CursorLine".to_string()),
+                    hi_name: Some("CursorLine".to_string()),
+                    id: Some(51),
+                },
+                HlInfo {
+                    kind: "syntax".to_string(),
+                    ui_name: None,
+                    hi_name: Some("Comment".to_string()),
+                    id: Some(20),
+                },
+                HlInfo {
+                    kind: "terminal".to_string(),
+                    ui_name: None,
+                    hi_name: None,
+                    id: None,
+                },
+            ],
+        }])];
+
+        let res = nvim_bridge::parse_redraw_event(args!(
+            "hl_attr_define".into(),
+            Value::Array(vec!(
+                7.into(),
+                Value::Map(vec!(("bold".into(), true.into()),)),
+                Value::Map(vec!()),
+                Value::Array(vec!(
+                    Value::Map(vec!(
+                        ("kind".into(), "ui".into()),
+                        ("ui_name".into(), "CursorLine".into()),
+                        ("hi_name".into(), "CursorLine".into()),
+                        ("id".into(), 51.into()),
+                    )),
+                    Value::Map(vec!(
+                        ("kind".into(), "syntax".into()),
+                        ("hi_name".into(), "Comment".into()),
+                        ("id".into(), 20.into()),
+                    )),
+                    Value::Map(vec!(("kind".into(), "terminal".into()),)),
+                )),
+            ))
+        ));
+
+        assert_eq!(expected, res);
+    }
+
     #[test]
     fn option_set() {
         let expected = vec![RedrawEvent::OptionSet(vec![
             OptionSet::GuiFont("my awesome font:h32".into()),
+            OptionSet::GuiFontWide("my wide font:h32".into()),
             OptionSet::LineSpace(32),
+            OptionSet::MouseHide(true),
+            OptionSet::PumBlend(20),
+            OptionSet::ShowTabline(2),
+            OptionSet::ExtMultigrid(false),
+            OptionSet::NotSupported("arabicshape".into()),
         ])];
 
         let res = nvim_bridge::parse_redraw_event(args!(
             "option_set".into(),
             Value::Array(vec!("guifont".into(), "my awesome font:h32".into(),)),
-            Value::Array(vec!("linespace".into(), 32.into()))
+            Value::Array(vec!("guifontwide".into(), "my wide font:h32".into())),
+            Value::Array(vec!("linespace".into(), 32.into())),
+            Value::Array(vec!("mousehide".into(), true.into())),
+            Value::Array(vec!("pumblend".into(), 20.into())),
+            Value::Array(vec!("showtabline".into(), 2.into())),
+            Value::Array(vec!("ext_multigrid".into(), false.into())),
+            Value::Array(vec!("arabicshape".into(), true.into()))
         ));
 
         assert_eq!(expected, res);
@@ -633,9 +796,150 @@ mod parse_redraw_event_tests {
         assert_eq!(expected, res);
     }
 
+    #[test]
+    fn win_viewport() {
+        let expected = vec![RedrawEvent::WinViewport(vec![
+            WinViewport {
+                grid: 2,
+                win: Value::Ext(1, vec![1]),
+                topline: 10,
+                botline: 40,
+                curline: 15,
+                curcol: 3,
+                line_count: Some(100),
+            },
+            WinViewport {
+                grid: 3,
+                win: Value::Ext(1, vec![2]),
+                topline: 0,
+                botline: 5,
+                curline: 0,
+                curcol: 0,
+                line_count: None,
+            },
+        ])];
+
+        let res = nvim_bridge::parse_redraw_event(args!(
+            "win_viewport".into(),
+            Value::Array(vec!(
+                2.into(),
+                Value::Ext(1, vec!(1)),
+                10.into(),
+                40.into(),
+                15.into(),
+                3.into(),
+                100.into(),
+            )),
+            Value::Array(vec!(
+                3.into(),
+                Value::Ext(1, vec!(2)),
+                0.into(),
+                5.into(),
+                0.into(),
+                0.into(),
+            ))
+        ));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn msg_show() {
+        let expected = vec![RedrawEvent::MsgShow(vec![MsgShow {
+            kind: "emsg".to_string(),
+            content: vec![(3, "E492: Not an editor command".to_string())],
+            replace_last: true,
+        }])];
+
+        let res = nvim_bridge::parse_redraw_event(args!(
+            "msg_show".into(),
+            Value::Array(vec!(
+                "emsg".into(),
+                Value::Array(vec!(Value::Array(vec!(
+                    3.into(),
+                    "E492: Not an editor command".into(),
+                )),)),
+                true.into(),
+            ))
+        ));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn msg_showmode() {
+        let expected = vec![RedrawEvent::MsgShowmode(vec![
+            vec![(7, "-- INSERT --".to_string())],
+            vec![],
+        ])];
+
+        let res = nvim_bridge::parse_redraw_event(args!(
+            "msg_showmode".into(),
+            Value::Array(vec!(Value::Array(vec!(Value::Array(vec!(
+                7.into(),
+                "-- INSERT --".into(),
+            )),)),)),
+            Value::Array(vec!(Value::Array(vec!()),))
+        ));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn msg_history_show() {
+        let expected =
+            vec![RedrawEvent::MsgHistoryShow(vec![MsgHistoryShow {
+                entries: vec![
+                    ("echomsg".to_string(), vec![(0, "foo".to_string())]),
+                    ("".to_string(), vec![(1, "bar".to_string())]),
+                ],
+            }])];
+
+        let res = nvim_bridge::parse_redraw_event(args!(
+            "msg_history_show".into(),
+            Value::Array(vec!(Value::Array(vec!(
+                Value::Array(vec!(
+                    "echomsg".into(),
+                    Value::Array(vec!(Value::Array(vec!(
+                        0.into(),
+                        "foo".into(),
+                    )),)),
+                )),
+                Value::Array(vec!(
+                    "".into(),
+                    Value::Array(vec!(Value::Array(vec!(
+                        1.into(),
+                        "bar".into(),
+                    )),)),
+                )),
+            )),))
+        ));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn msg_history_clear() {
+        let expected = vec![RedrawEvent::MsgHistoryClear()];
+
+        let res =
+            nvim_bridge::parse_redraw_event(args!("msg_history_clear".into()));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn msg_clear() {
+        let expected = vec![RedrawEvent::MsgClear()];
+
+        let res = nvim_bridge::parse_redraw_event(args!("msg_clear".into()));
+
+        assert_eq!(expected, res);
+    }
+
     #[test]
     fn mouse_on() {
-        let expected = vec![RedrawEvent::Ignored("mouse_on".to_owned())];
+        let expected = vec![RedrawEvent::MouseOn()];
 
         let res = nvim_bridge::parse_redraw_event(args!("mouse_on".into()));
 
@@ -644,12 +948,63 @@ mod parse_redraw_event_tests {
 
     #[test]
     fn mouse_off() {
-        let expected = vec![RedrawEvent::Ignored("mouse_off".to_owned())];
+        let expected = vec![RedrawEvent::MouseOff()];
 
         let res = nvim_bridge::parse_redraw_event(args!("mouse_off".into()));
 
         assert_eq!(expected, res);
     }
+
+    #[test]
+    fn bell() {
+        let expected = vec![RedrawEvent::Bell()];
+
+        let res = nvim_bridge::parse_redraw_event(args!("bell".into()));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn visual_bell() {
+        let expected = vec![RedrawEvent::VisualBell()];
+
+        let res = nvim_bridge::parse_redraw_event(args!("visual_bell".into()));
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn malformed_event_doesnt_stop_the_batch() {
+        let res = nvim_bridge::parse_redraw_event(vec![
+            Value::Array(vec![
+                "grid_resize".into(),
+                Value::Array(vec![1.into(), "not a number".into(), 2.into()]),
+            ]),
+            Value::Array(vec![
+                "grid_cursor_goto".into(),
+                Value::Array(vec![1.into(), 2.into()]),
+            ]),
+            Value::Array(vec![1.into()]),
+            Value::Array(vec!["flush".into()]),
+        ]);
+
+        assert_eq!(res.len(), 4);
+        match &res[0] {
+            RedrawEvent::Malformed(name, _) => assert_eq!(name, "grid_resize"),
+            e => panic!("Unexpected event: {:?}", e),
+        }
+        match &res[1] {
+            RedrawEvent::Malformed(name, _) => {
+                assert_eq!(name, "grid_cursor_goto")
+            }
+            e => panic!("Unexpected event: {:?}", e),
+        }
+        match &res[2] {
+            RedrawEvent::Malformed(name, _) => assert_eq!(name, ""),
+            e => panic!("Unexpected event: {:?}", e),
+        }
+        assert_eq!(res[3], RedrawEvent::Flush());
+    }
 }
 
 mod parse_gnvim_event_tests {
@@ -754,4 +1109,155 @@ mod parse_gnvim_event_tests {
             assert_eq!(expected, res);
         }
     }
+
+    #[test]
+    fn enable_ext_messages() {
+        let expected: Result<GnvimEvent, String> =
+            Ok(GnvimEvent::EnableExtMessages(true));
+
+        let res = nvim_bridge::parse_gnvim_event(vec![
+            "EnableExtMessages".into(),
+            1.into(),
+        ]);
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn enable_ext_multigrid() {
+        let expected: Result<GnvimEvent, String> =
+            Ok(GnvimEvent::EnableExtMultigrid(false));
+
+        let res = nvim_bridge::parse_gnvim_event(vec![
+            "EnableExtMultigrid".into(),
+            0.into(),
+        ]);
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn new_window() {
+        let expected: Result<GnvimEvent, String> = Ok(GnvimEvent::NewWindow);
+
+        let res = nvim_bridge::parse_gnvim_event(vec!["NewWindow".into()]);
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn open() {
+        let expected: Result<GnvimEvent, String> = Ok(GnvimEvent::Open);
+
+        let res = nvim_bridge::parse_gnvim_event(vec!["Open".into()]);
+
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn api_info() {
+        let info = nvim_bridge::api_info();
+        let get = |key: &str| {
+            info.as_map()
+                .unwrap()
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(key))
+                .map(|(_, v)| v.clone())
+                .unwrap()
+        };
+        let has = |key: &str, name: &str| {
+            get(key).as_array().unwrap().contains(&Value::from(name))
+        };
+
+        assert_eq!(get("version"), Value::from(nvim_bridge::API_VERSION));
+        assert!(has("events", "NewWindow"));
+        assert!(has("requests", "ApiInfo"));
+        assert!(has("requests", "CursorHlNames"));
+        assert!(!has("events", "Unknown"));
+
+        let tooltip = cfg!(feature = "libwebkit2gtk");
+        assert_eq!(has("features", "cursor_tooltip"), tooltip);
+        assert_eq!(has("events", "CursorTooltipShow"), tooltip);
+        assert_eq!(has("requests", "CursorTooltipGetStyles"), tooltip);
+    }
+
+    #[test]
+    fn dir_changed() {
+        let expected: Result<GnvimEvent, String> =
+            Ok(GnvimEvent::DirChanged("/tmp".to_owned()));
+
+        let res = nvim_bridge::parse_gnvim_event(vec![
+            "DirChanged".into(),
+            "/tmp".into(),
+        ]);
+
+        assert_eq!(expected, res);
+    }
+}
+
+mod nvim_info_tests {
+
+    use std::convert::TryFrom;
+
+    use crate::nvim_bridge::NvimInfo;
+    use rmpv::Value;
+
+    fn metadata(ui_options: Option<Vec<&str>>) -> Value {
+        let mut metadata = vec![(
+            "version".into(),
+            Value::Map(vec![
+                ("major".into(), 0.into()),
+                ("minor".into(), 4.into()),
+                ("patch".into(), 3.into()),
+                ("api_level".into(), 6.into()),
+                ("api_compatible".into(), 0.into()),
+                ("api_prerelease".into(), false.into()),
+            ]),
+        )];
+        if let Some(opts) = ui_options {
+            metadata.push((
+                "ui_options".into(),
+                Value::Array(opts.into_iter().map(Value::from).collect()),
+            ));
+        }
+
+        Value::Map(metadata)
+    }
+
+    #[test]
+    fn nvim_info() {
+        let info = NvimInfo::try_from(metadata(Some(vec![
+            "rgb",
+            "ext_linegrid",
+            "ext_multigrid",
+        ])))
+        .unwrap();
+
+        assert_eq!(
+            info,
+            NvimInfo {
+                version: "0.4.3".into(),
+                api_level: 6,
+                ui_options: vec![
+                    "rgb".into(),
+                    "ext_linegrid".into(),
+                    "ext_multigrid".into()
+                ],
+            }
+        );
+        assert!(info.supports_ui_option("ext_multigrid"));
+        assert!(!info.supports_ui_option("ext_messages"));
+    }
+
+    #[test]
+    fn nvim_info_without_ui_options() {
+        let info = NvimInfo::try_from(metadata(None)).unwrap();
+
+        assert!(info.ui_options.is_empty());
+    }
+
+    #[test]
+    fn nvim_info_invalid() {
+        assert!(NvimInfo::try_from(Value::from("foo")).is_err());
+    }
 }
@@ -4,24 +4,67 @@ use std::{
     task::{Context, Poll},
 };
 
+use gio::prelude::*;
 use pin_project::pin_project;
 
+use crate::nvim_gio::Error;
 use crate::thread_guard::ThreadGuard;
 
 #[pin_project]
 pub struct Compat<T> {
     #[pin]
     inner: ThreadGuard<T>,
+    /// Socket connection that `inner` belongs to, if any. We need to hold on
+    /// to it, otherwise the connection gets closed once it is dropped.
+    #[allow(unused)]
+    conn: Option<ThreadGuard<gio::SocketConnection>>,
 }
 
 impl<T> Compat<T> {
     pub fn new(inner: T) -> Self {
         Self {
             inner: ThreadGuard::new(inner),
+            conn: None,
         }
     }
 }
 
+impl Compat<gio::InputStreamAsyncRead<gio::PollableInputStream>> {
+    /// Creates a reader for the receiving end of `conn`.
+    pub fn from_socket(conn: &gio::SocketConnection) -> Result<Self, Error> {
+        let input = conn
+            .get_input_stream()
+            .ok_or(Error::Socket)?
+            .dynamic_cast::<gio::PollableInputStream>()
+            .map_err(|_| Error::ToPollaple)?;
+
+        Ok(Self {
+            inner: ThreadGuard::new(
+                input.into_async_read().map_err(|_| Error::ToAsync)?,
+            ),
+            conn: Some(ThreadGuard::new(conn.clone())),
+        })
+    }
+}
+
+impl Compat<gio::OutputStreamAsyncWrite<gio::PollableOutputStream>> {
+    /// Creates a writer for the sending end of `conn`.
+    pub fn from_socket(conn: &gio::SocketConnection) -> Result<Self, Error> {
+        let output = conn
+            .get_output_stream()
+            .ok_or(Error::Socket)?
+            .dynamic_cast::<gio::PollableOutputStream>()
+            .map_err(|_| Error::ToPollaple)?;
+
+        Ok(Self {
+            inner: ThreadGuard::new(
+                output.into_async_write().map_err(|_| Error::ToAsync)?,
+            ),
+            conn: Some(ThreadGuard::new(conn.clone())),
+        })
+    }
+}
+
 impl futures::io::AsyncRead
     for Compat<gio::InputStreamAsyncRead<gio::PollableInputStream>>
 {
@@ -0,0 +1,312 @@
+//! In-process stand-in for nvim, for testing the UI without a real nvim.
+
+use std::io::{self, Write};
+use std::os::unix::net::UnixStream;
+use std::panic;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use gio::prelude::*;
+use rmpv::Value;
+
+use crate::nvim_bridge::{Message, NvimBridge};
+use crate::nvim_gio::{start_io, Error, GioNeovim, GioReader, GioWriter};
+
+/// Rpc request or notify that gnvim sent to the fake nvim.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// Name of the api function (e.g. `nvim_input`).
+    pub method: String,
+    pub args: Vec<Value>,
+}
+
+/// Fake nvim that records all the calls made to it and sends scripted
+/// redraw events to gnvim. Requests are responded with a plausible "nothing
+/// happened" value.
+///
+/// Dropping the fake nvim closes the connection, like nvim exiting would.
+pub struct FakeNvim {
+    /// Our end of the connection, for sending notifies to gnvim.
+    stream: Arc<Mutex<UnixStream>>,
+    calls: Arc<Mutex<Vec<Call>>>,
+}
+
+impl FakeNvim {
+    /// Creates a fake nvim and a neovim instance connected to it. Events
+    /// from the fake nvim are sent to `tx`, like `new_child` does.
+    ///
+    /// Must be called on the thread that owns the default main context
+    /// (see `run_on_main_thread`).
+    pub fn start(
+        tx: glib::Sender<Message>,
+    ) -> Result<(FakeNvim, GioNeovim), Error> {
+        let (ours, theirs) = UnixStream::pair().map_err(|_| Error::Socket)?;
+
+        let socket = unsafe { gio::Socket::from_fd(theirs)? };
+        let conn = socket
+            .connection_factory_create_connection()
+            .ok_or(Error::Socket)?;
+        let read = GioReader::from_socket(&conn)?;
+        let write = GioWriter::from_socket(&conn)?;
+        let nvim = start_io(NvimBridge::new(tx.clone()), read, write, tx, None);
+
+        let reader = ours.try_clone().map_err(|_| Error::Socket)?;
+        let stream = Arc::new(Mutex::new(ours));
+        let calls = Arc::new(Mutex::new(vec![]));
+
+        let (stream_ref, calls_ref) = (stream.clone(), calls.clone());
+        thread::spawn(move || serve(reader, stream_ref, calls_ref));
+
+        Ok((FakeNvim { stream, calls }, nvim))
+    }
+
+    /// Sends a redraw notify to gnvim.
+    ///
+    /// * `events` - Redraw events, each in the form of
+    ///              `[name, [args]...]` (see `:h ui-events`).
+    pub fn redraw(&self, events: Vec<Value>) {
+        self.notify("redraw", events);
+    }
+
+    /// Sends a notify to gnvim.
+    pub fn notify(&self, method: &str, args: Vec<Value>) {
+        let msg =
+            Value::Array(vec![2.into(), method.into(), Value::Array(args)]);
+        write_msg(&self.stream, &msg).expect("Failed to write notify");
+    }
+
+    /// All the calls made so far.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Calls made so far to `method`.
+    pub fn calls_to(&self, method: &str) -> Vec<Call> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.method == method)
+            .collect()
+    }
+
+    /// Forgets the calls made so far.
+    pub fn clear_calls(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    /// Runs the default main context until `f` returns true for the calls
+    /// made so far, or until `timeout` (in milliseconds) has passed. Returns
+    /// the last value of `f`.
+    pub fn wait_for<F>(&self, timeout: u64, f: F) -> bool
+    where
+        F: Fn(&[Call]) -> bool,
+    {
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        loop {
+            if f(&self.calls.lock().unwrap()) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+
+            run_for(1);
+        }
+    }
+
+    /// Like `wait_for`, but waits for a call to `method`.
+    pub fn wait_for_call(&self, timeout: u64, method: &str) -> bool {
+        self.wait_for(timeout, |calls| {
+            calls.iter().any(|call| call.method == method)
+        })
+    }
+}
+
+impl Drop for FakeNvim {
+    fn drop(&mut self) {
+        let _ = self
+            .stream
+            .lock()
+            .unwrap()
+            .shutdown(std::net::Shutdown::Both);
+    }
+}
+
+/// Reads the rpc messages from gnvim, until the connection is closed.
+fn serve(
+    mut reader: UnixStream,
+    stream: Arc<Mutex<UnixStream>>,
+    calls: Arc<Mutex<Vec<Call>>>,
+) {
+    while let Ok(msg) = rmpv::decode::read_value(&mut reader) {
+        let msg = match msg {
+            Value::Array(msg) => msg,
+            _ => continue,
+        };
+
+        match msg.as_slice() {
+            // Request.
+            [_, id, method, Value::Array(args)] => {
+                let method = method.as_str().unwrap_or_default().to_string();
+                let res = response(&method, &args);
+                calls.lock().unwrap().push(Call {
+                    method,
+                    args: args.clone(),
+                });
+
+                let msg =
+                    Value::Array(vec![1.into(), id.clone(), Value::Nil, res]);
+                if write_msg(&stream, &msg).is_err() {
+                    return;
+                }
+            }
+            // Notify.
+            [_, method, Value::Array(args)] => {
+                calls.lock().unwrap().push(Call {
+                    method: method.as_str().unwrap_or_default().to_string(),
+                    args: args.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Response for a request, that fits to what the real nvim would return.
+fn response(method: &str, args: &[Value]) -> Value {
+    match (method, args) {
+        // Number of bytes written.
+        ("nvim_input", [Value::String(s)]) => {
+            (s.as_bytes().len() as u64).into()
+        }
+        // Line count of a window's buffer.
+        ("nvim_call_function", [Value::String(f), _])
+            if f.as_str() == Some("line") =>
+        {
+            100.into()
+        }
+        // Viewport of the current window, see `Scrollbar::query_viewport`.
+        ("nvim_eval", [Value::String(expr)])
+            if expr.as_str().map_or(false, |e| e.contains("line('w0')")) =>
+        {
+            Value::Array(vec![1000.into(), 0.into(), 30.into(), 100.into()])
+        }
+        _ => Value::Nil,
+    }
+}
+
+fn write_msg(stream: &Mutex<UnixStream>, msg: &Value) -> io::Result<()> {
+    let mut stream = stream.lock().unwrap();
+    rmpv::encode::write_value(&mut *stream, msg)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    stream.flush()
+}
+
+/// Runs the default main context for `ms` milliseconds.
+pub fn run_for(ms: u64) {
+    let c = glib::MainContext::default();
+    let deadline = Instant::now() + Duration::from_millis(ms);
+    loop {
+        while c.iteration(false) {}
+
+        if Instant::now() >= deadline {
+            break;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Sender to the thread that owns the default main context (and gtk, if it
+/// could be initialized).
+static MAIN_THREAD: Mutex<Option<mpsc::Sender<Job>>> = Mutex::new(None);
+
+/// Runs `f` on the thread that owns the default main context and waits for
+/// it to finish. Tests are run on multiple threads, but the main context
+/// and gtk can only be used from one.
+///
+/// * `f` - Gets a flag telling if gtk was initialized.
+pub fn run_on_main_thread<F>(f: F)
+where
+    F: FnOnce(bool) + Send + 'static,
+{
+    let (res_tx, res_rx) = mpsc::channel();
+    let job: Job = Box::new(move || {
+        let gtk = gtk::is_initialized_main_thread();
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| f(gtk)));
+        let _ = res_tx.send(res);
+    });
+
+    MAIN_THREAD
+        .lock()
+        .unwrap()
+        .get_or_insert_with(spawn_main_thread)
+        .send(job)
+        .expect("Main thread is gone");
+
+    if let Err(err) = res_rx.recv().expect("Main thread is gone") {
+        panic::resume_unwind(err);
+    }
+}
+
+fn spawn_main_thread() -> mpsc::Sender<Job> {
+    let (tx, rx) = mpsc::channel::<Job>();
+
+    thread::spawn(move || {
+        // Without a display, we can still run the main context.
+        if gtk::init().is_err() {
+            glib::MainContext::default().acquire();
+        }
+
+        for job in rx {
+            job();
+        }
+    });
+
+    tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::nvim_bridge::{Notify, RedrawEvent};
+
+    #[test]
+    fn test_fake_nvim() {
+        run_on_main_thread(|_| {
+            let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+            let (fake, nvim) = FakeNvim::start(tx).unwrap();
+
+            let events = Rc::new(RefCell::new(vec![]));
+            let events_ref = events.clone();
+            rx.attach(None, move |msg| {
+                if let Message::Notify(Notify::RedrawEvent(evt)) = msg {
+                    events_ref.borrow_mut().extend(evt);
+                }
+                glib::Continue(true)
+            });
+
+            fake.redraw(vec![Value::Array(vec!["flush".into()])]);
+            glib::MainContext::default().spawn_local(async move {
+                assert_eq!(nvim.input("ihello").await.unwrap(), 6);
+            });
+
+            assert!(fake.wait_for_call(1000, "nvim_input"));
+            assert_eq!(
+                fake.calls(),
+                vec![Call {
+                    method: String::from("nvim_input"),
+                    args: vec!["ihello".into()],
+                }]
+            );
+
+            run_for(10);
+            assert_eq!(*events.borrow(), vec![RedrawEvent::Flush()]);
+        });
+    }
+}
@@ -1,13 +1,20 @@
 use gio::prelude::*;
 
-use log::error;
+use log::debug;
 
 use nvim_rs::{create::Spawner, neovim::Neovim, Handler};
 
 use crate::nvim_bridge;
 
 pub mod compat;
+#[cfg(test)]
+pub mod fake;
+mod stderr;
 
+pub use self::stderr::StderrLog;
+
+pub type GioReader =
+    Compat<gio::InputStreamAsyncRead<gio::PollableInputStream>>;
 pub type GioWriter =
     Compat<gio::OutputStreamAsyncWrite<gio::PollableOutputStream>>;
 pub type GioNeovim = Neovim<GioWriter>;
@@ -15,15 +22,18 @@ pub type GioNeovim = Neovim<GioWriter>;
 #[derive(Debug)]
 pub enum Error {
     Pipe,
+    Socket,
     ToPollaple,
     ToAsync,
     GlibError(glib::Error),
+    Connect(String, glib::Error),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::Pipe => write!(fmt, "Failed to open pipe to subprocess"),
+            Error::Socket => write!(fmt, "Failed to open socket stream"),
             Error::ToPollaple => {
                 write!(fmt, "Failed to turn pipe into pollable stream")
             }
@@ -33,6 +43,9 @@ impl std::fmt::Display for Error {
             Error::GlibError(e) => {
                 write!(fmt, "Failed to open nvim subprocess: {}", e)
             }
+            Error::Connect(addr, e) => {
+                write!(fmt, "Failed to connect to nvim at '{}': {}", addr, e)
+            }
         }
     }
 }
@@ -45,10 +58,18 @@ impl From<glib::Error> for Error {
 
 use compat::Compat;
 
+/// How long (in milliseconds) we'll wait for nvim's stderr to close after
+/// something went wrong.
+pub const STDERR_TIMEOUT: u32 = 500;
+
+/// Spawns nvim as a child process.
+///
+/// * `stderr` - Log where nvim's stderr is captured to.
 pub fn new_child<H>(
     handler: H,
     args: Vec<&std::ffi::OsStr>,
     tx: glib::Sender<nvim_bridge::Message>,
+    stderr: &StderrLog,
 ) -> Result<GioNeovim, Error>
 where
     H: Spawner + Handler<Writer = GioWriter>,
@@ -76,18 +97,207 @@ where
     let read =
         Compat::new(output.into_async_read().map_err(|_| Error::ToAsync)?);
 
-    let (neovim, io) = Neovim::<
-        Compat<gio::OutputStreamAsyncWrite<gio::PollableOutputStream>>,
-    >::new(read, write, handler);
+    stderr.capture(p.get_stderr_pipe().ok_or(Error::Pipe)?)?;
+
+    Ok(start_io(
+        handler,
+        read,
+        write,
+        tx,
+        Some((p, stderr.clone())),
+    ))
+}
+
+/// Creates a neovim instance that isn't connected to any nvim (e.g. for
+/// replaying a recording). Anything sent to it is discarded, and requests
+/// never get a response.
+pub fn new_offline<H>(handler: H) -> Result<GioNeovim, Error>
+where
+    H: Spawner + Handler<Writer = GioWriter>,
+{
+    let input = gio::MemoryOutputStream::new_resizable()
+        .dynamic_cast::<gio::PollableOutputStream>()
+        .map_err(|_| Error::ToPollaple)?;
+    let write =
+        Compat::new(input.into_async_write().map_err(|_| Error::ToAsync)?);
+
+    let output = gio::MemoryInputStream::new()
+        .dynamic_cast::<gio::PollableInputStream>()
+        .map_err(|_| Error::ToPollaple)?;
+    let read =
+        Compat::new(output.into_async_read().map_err(|_| Error::ToAsync)?);
+
+    let (neovim, io) = Neovim::<GioWriter>::new(read, write, handler);
+
+    let c = glib::MainContext::default();
+    c.spawn_local(async move {
+        let _ = io.await;
+    });
+
+    Ok(neovim)
+}
+
+/// Wraps `args` (nvim command line) to be run on `dest` with ssh. The
+/// result can be passed to `new_child`.
+///
+/// * `dest` - Ssh destination (e.g. `user@host`).
+pub fn ssh_args(dest: &str, args: &[&str]) -> Vec<String> {
+    // Ssh joins the command with spaces and passes it to the remote shell,
+    // so the arguments need to be quoted.
+    let cmd = args
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    // "--" keeps a `dest` starting with "-" from being taken as an option.
+    vec![
+        String::from("ssh"),
+        String::from("--"),
+        String::from(dest),
+        cmd,
+    ]
+}
+
+/// Quotes `arg` for posix shells.
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Connects to an already running nvim (see `:h --listen`).
+///
+/// * `addr` - Either a path to a unix socket or a `host:port` pair.
+pub async fn new_connection<H>(
+    handler: H,
+    addr: &str,
+    tx: glib::Sender<nvim_bridge::Message>,
+) -> Result<GioNeovim, Error>
+where
+    H: Spawner + Handler<Writer = GioWriter>,
+{
+    let client = gio::SocketClient::new();
+
+    let conn = if is_tcp_address(addr) {
+        client.connect_to_host_async_future(addr, 0).await
+    } else {
+        let sock_addr = gio::UnixSocketAddress::new(std::path::Path::new(addr));
+        client.connect_async_future(&sock_addr).await
+    }
+    .map_err(|err| Error::Connect(addr.to_string(), err))?;
+
+    let read = GioReader::from_socket(&conn)?;
+    let write = GioWriter::from_socket(&conn)?;
+
+    Ok(start_io(handler, read, write, tx, None))
+}
+
+/// Creates the neovim instance and starts processing the rpc messages
+/// on the main context. Once the rpc connection is closed, `Message::Close`
+/// is sent to `tx`.
+///
+/// * `child` - The nvim process and its stderr log, if we spawned it. Used
+///             to report nvim's exit status.
+fn start_io<H>(
+    handler: H,
+    read: GioReader,
+    write: GioWriter,
+    tx: glib::Sender<nvim_bridge::Message>,
+    child: Option<(gio::Subprocess, StderrLog)>,
+) -> GioNeovim
+where
+    H: Spawner + Handler<Writer = GioWriter>,
+{
+    let (neovim, io) = Neovim::<GioWriter>::new(read, write, handler);
 
     let c = glib::MainContext::default();
 
-    c.spawn(async move {
+    c.spawn_local(async move {
         let _ = io.await;
-        if let Err(err) = tx.send(nvim_bridge::Message::Close) {
-            error!("Failed to send close message to the gui: {}", err)
+
+        let info = match child {
+            Some((p, stderr)) => {
+                let reason = exit_reason(&p).await;
+                let stderr = if p.get_successful() {
+                    None
+                } else {
+                    stderr.wait_closed(STDERR_TIMEOUT).await;
+                    Some(stderr.contents())
+                };
+
+                nvim_bridge::CloseInfo { reason, stderr }
+            }
+            None => nvim_bridge::CloseInfo {
+                reason: String::from("Connection to nvim was closed"),
+                stderr: None,
+            },
+        };
+
+        // The window might be gone already, if it was closed first.
+        if let Err(err) = tx.send(nvim_bridge::Message::Close(info)) {
+            debug!("Failed to send close message to the gui: {}", err)
         }
     });
 
-    Ok(neovim)
+    neovim
+}
+
+/// Waits for `p` to exit and describes how it went.
+async fn exit_reason(p: &gio::Subprocess) -> String {
+    if let Err(err) = p.wait_async_future().await {
+        return format!("Failed to wait for nvim to exit: {}", err);
+    }
+
+    if p.get_if_exited() {
+        format!("Nvim exited with status {}", p.get_exit_status())
+    } else if p.get_if_signaled() {
+        format!("Nvim was terminated by signal {}", p.get_term_sig())
+    } else {
+        String::from("Nvim exited")
+    }
+}
+
+/// Nvim accepts `host:port` or a path (to a unix socket) as its `--listen`
+/// address. Anything that has a port but doesn't look like a path is
+/// considered as a tcp address.
+fn is_tcp_address(addr: &str) -> bool {
+    if addr.contains('/') {
+        return false;
+    }
+
+    match addr.rfind(':') {
+        Some(i) => addr[i + 1..].parse::<u16>().is_ok(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssh_args() {
+        assert_eq!(
+            ssh_args("user@host", &["nvim", "--cmd", "let g:gnvim=1"]),
+            vec!["ssh", "--", "user@host", "'nvim' '--cmd' 'let g:gnvim=1'"]
+        );
+        assert_eq!(
+            ssh_args("host", &["nvim", "it's"]),
+            vec!["ssh", "--", "host", "'nvim' 'it'\\''s'"]
+        );
+        assert_eq!(
+            ssh_args("-oProxyCommand=sh", &["nvim"]),
+            vec!["ssh", "--", "-oProxyCommand=sh", "'nvim'"]
+        );
+    }
+
+    #[test]
+    fn test_is_tcp_address() {
+        assert!(is_tcp_address("localhost:6666"));
+        assert!(is_tcp_address("127.0.0.1:6666"));
+        assert!(is_tcp_address("[::1]:6666"));
+        assert!(!is_tcp_address("/tmp/nvimsocket"));
+        assert!(!is_tcp_address("./nvim:6666"));
+        assert!(!is_tcp_address("nvimsocket"));
+        assert!(!is_tcp_address("localhost:port"));
+    }
 }
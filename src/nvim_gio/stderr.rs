@@ -0,0 +1,96 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use futures::channel::oneshot;
+use futures::io::{AsyncBufReadExt, BufReader};
+use futures::stream::StreamExt;
+use gio::prelude::*;
+use log::debug;
+
+use crate::nvim_gio::Error;
+
+/// How many lines of nvim's stderr we keep around.
+const MAX_LINES: usize = 200;
+
+/// Ring buffer for the latest lines that nvim has written to its stderr.
+#[derive(Clone, Default)]
+pub struct StderrLog {
+    lines: Rc<RefCell<VecDeque<String>>>,
+    /// Resolves once the stderr pipe is closed (e.g. nvim has exited).
+    closed: Rc<RefCell<Option<oneshot::Receiver<()>>>>,
+}
+
+impl StderrLog {
+    /// Starts reading `stream` into the log.
+    pub(super) fn capture(
+        &self,
+        stream: gio::InputStream,
+    ) -> Result<(), Error> {
+        let read = stream
+            .dynamic_cast::<gio::PollableInputStream>()
+            .map_err(|_| Error::ToPollaple)?
+            .into_async_read()
+            .map_err(|_| Error::ToAsync)?;
+
+        let (tx, rx) = oneshot::channel();
+        self.closed.replace(Some(rx));
+
+        let log = self.clone();
+        let c = glib::MainContext::default();
+        c.spawn_local(async move {
+            let mut lines = BufReader::new(read).lines();
+            while let Some(Ok(line)) = lines.next().await {
+                debug!("nvim stderr: {}", line);
+                log.push(line);
+            }
+
+            let _ = tx.send(());
+        });
+
+        Ok(())
+    }
+
+    fn push(&self, line: String) {
+        let mut lines = self.lines.borrow_mut();
+        if lines.len() >= MAX_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Waits (at most `timeout` milliseconds) for the stderr pipe to close,
+    /// so we can be sure that we have everything nvim wrote before exiting.
+    pub async fn wait_closed(&self, timeout: u32) {
+        let closed = self.closed.borrow_mut().take();
+        if let Some(closed) = closed {
+            futures::future::select(closed, glib::timeout_future(timeout))
+                .await;
+        }
+    }
+
+    /// Returns the captured lines.
+    pub fn contents(&self) -> String {
+        let lines = self.lines.borrow();
+        lines.iter().cloned().collect::<Vec<String>>().join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stderr_log_drops_oldest_lines() {
+        let log = StderrLog::default();
+        for i in 0..MAX_LINES + 2 {
+            log.push(i.to_string());
+        }
+
+        let contents = log.contents();
+        let mut lines = contents.lines();
+        assert_eq!(lines.next(), Some("2"));
+        assert_eq!(lines.last(), Some((MAX_LINES + 1).to_string().as_str()));
+        assert_eq!(contents.lines().count(), MAX_LINES);
+    }
+}
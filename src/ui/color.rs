@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::nvim_bridge::HlInfo;
+
 #[derive(Hash, PartialEq, Eq)]
 pub enum HlGroup {
     Pmenu,
@@ -18,11 +20,29 @@ pub enum HlGroup {
     MsgSeparator,
 }
 
+impl HlGroup {
+    /// Name of nvim's builtin highlight group that the group is styled with.
+    fn name(&self) -> &'static str {
+        match self {
+            HlGroup::Pmenu | HlGroup::Wildmenu => "Pmenu",
+            HlGroup::PmenuSel | HlGroup::WildmenuSel => "PmenuSel",
+            HlGroup::Tabline => "TabLine",
+            HlGroup::TablineSel | HlGroup::CmdlineBorder => "TabLineSel",
+            HlGroup::TablineFill => "TabLineFill",
+            HlGroup::Cmdline => "Normal",
+            HlGroup::MsgSeparator => "MsgSeparator",
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct HlDefs {
     hl_defs: HashMap<u64, Highlight>,
 
-    hl_groups: HashMap<HlGroup, u64>,
+    /// Sources of each highlight (see `:h ui-hlstate`).
+    hl_infos: HashMap<u64, Vec<HlInfo>>,
+    /// Highlight ids of the builtin UI highlight groups, by name.
+    hl_group_names: HashMap<String, u64>,
 
     pub default_fg: Color,
     pub default_bg: Color,
@@ -42,16 +62,44 @@ impl HlDefs {
         self.hl_defs.insert(id, hl)
     }
 
-    pub fn set_hl_group(&mut self, group: HlGroup, id: u64) -> Option<u64> {
-        self.hl_groups.insert(group, id)
+    pub fn get_hl_group(&self, group: &HlGroup) -> Option<&Highlight> {
+        self.get_hl_group_by_name(group.name())
     }
 
-    pub fn get_hl_group(&self, group: &HlGroup) -> Option<&Highlight> {
-        if let Some(id) = self.hl_groups.get(group) {
-            return self.hl_defs.get(id);
+    pub fn set_hl_group_name(&mut self, name: String, id: u64) -> Option<u64> {
+        self.hl_group_names.insert(name, id)
+    }
+
+    /// Returns the highlight of a builtin UI highlight group (e.g.
+    /// `StatusLine`).
+    pub fn get_hl_group_by_name(&self, name: &str) -> Option<&Highlight> {
+        self.hl_group_names
+            .get(name)
+            .and_then(|id| self.hl_defs.get(id))
+    }
+
+    pub fn insert_info(&mut self, id: u64, info: Vec<HlInfo>) {
+        if info.is_empty() {
+            self.hl_infos.remove(&id);
+        } else {
+            self.hl_infos.insert(id, info);
         }
+    }
+
+    /// Returns where the highlight `id` comes from. Empty, unless
+    /// `ext_hlstate` is enabled.
+    pub fn get_info(&self, id: &u64) -> &[HlInfo] {
+        self.hl_infos.get(id).map(Vec::as_slice).unwrap_or(&[])
+    }
 
-        None
+    /// Returns the names of the highlight groups that the highlight `id`
+    /// is made of (e.g. `["CursorLine", "Comment"]`).
+    pub fn get_names(&self, id: &u64) -> Vec<&str> {
+        self.get_info(id)
+            .iter()
+            .filter_map(|info| info.hi_name.as_ref().or(info.ui_name.as_ref()))
+            .map(String::as_str)
+            .collect()
     }
 }
 
@@ -66,6 +114,16 @@ pub struct Highlight {
     pub bold: bool,
     pub underline: bool,
     pub undercurl: bool,
+    pub underdouble: bool,
+    pub underdotted: bool,
+    pub underdashed: bool,
+    pub strikethrough: bool,
+    /// Alternative font. We don't have one, so this is drawn with the
+    /// normal font.
+    pub altfont: bool,
+    /// Don't combine with other highlights. Nvim handles this for us, but
+    /// we keep it around for completeness.
+    pub nocombine: bool,
 
     /// The blend value in range of 0..1.
     pub blend: f64,
@@ -84,10 +142,14 @@ impl Highlight {
         let sp = self.special.as_ref().unwrap_or(default_sp);
 
         let weight = if self.bold { "bold" } else { "normal" };
+        // Pango markup doesn't have dotted or dashed underlines, so use the
+        // single underline for those.
         let underline = if self.undercurl {
             "error"
-        } else if self.underline {
-            "underline"
+        } else if self.underdouble {
+            "double"
+        } else if self.underline || self.underdotted || self.underdashed {
+            "single"
         } else {
             "none"
         };
@@ -99,6 +161,8 @@ impl Highlight {
             foreground=\"#{fg}\"
             background=\"#{bg}\"
             underline_color=\"#{sp}\"
+            strikethrough=\"{strikethrough}\"
+            strikethrough_color=\"#{sp}\"
             weight=\"{weight}\"
             font_style=\"{fontstyle}\"
             underline=\"{underline}\">{text}</span>",
@@ -108,6 +172,7 @@ impl Highlight {
             weight = weight,
             fontstyle = fontstyle,
             underline = underline,
+            strikethrough = self.strikethrough,
             text = glib::markup_escape_text(text)
         )
     }
@@ -193,4 +258,67 @@ mod tests {
 
         assert_eq!(c.to_rgba(0.4), "rgba(255, 0, 255, 0.6)");
     }
+
+    #[test]
+    fn test_pango_markup_decorations() {
+        let hl = Highlight {
+            underdouble: true,
+            strikethrough: true,
+            ..Highlight::default()
+        };
+        let c = Color::default();
+
+        let markup = hl.pango_markup("foo", &c, &c, &c);
+        assert!(markup.contains("underline=\"double\""));
+        assert!(markup.contains("strikethrough=\"true\""));
+
+        let hl = Highlight {
+            underdotted: true,
+            ..Highlight::default()
+        };
+        let markup = hl.pango_markup("foo", &c, &c, &c);
+        assert!(markup.contains("underline=\"single\""));
+        assert!(markup.contains("strikethrough=\"false\""));
+    }
+
+    #[test]
+    fn test_hl_group_by_name() {
+        let mut hl_defs = HlDefs::default();
+        let hl = Highlight {
+            bold: true,
+            ..Highlight::default()
+        };
+        hl_defs.insert(5, hl);
+        hl_defs.set_hl_group_name(String::from("PmenuSel"), 5);
+
+        assert_eq!(hl_defs.get_hl_group(&HlGroup::PmenuSel), Some(&hl));
+        assert_eq!(hl_defs.get_hl_group(&HlGroup::WildmenuSel), Some(&hl));
+        assert_eq!(hl_defs.get_hl_group(&HlGroup::Pmenu), None);
+    }
+
+    #[test]
+    fn test_hl_defs_names() {
+        let mut hl_defs = HlDefs::default();
+        hl_defs.insert(3, Highlight::default());
+        hl_defs.insert_info(
+            3,
+            vec![
+                HlInfo {
+                    kind: String::from("ui"),
+                    ui_name: Some(String::from("StatusLine")),
+                    hi_name: None,
+                    id: None,
+                },
+                HlInfo {
+                    kind: String::from("syntax"),
+                    ui_name: None,
+                    hi_name: Some(String::from("Comment")),
+                    id: Some(20),
+                },
+            ],
+        );
+
+        assert_eq!(hl_defs.get_names(&3), vec!["StatusLine", "Comment"]);
+        assert!(hl_defs.get_names(&4).is_empty());
+    }
 }
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use futures::future::Future;
 
 pub fn spawn_local<F: Future<Output = ()> + 'static>(f: F) {
@@ -62,11 +64,75 @@ pub fn get_preferred_vertical_position(
     (y, height)
 }
 
+/// Escapes `name` to be used as a file argument in nvim commands
+/// (like `fnameescape()` does).
+pub fn escape_filename(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        match c {
+            ' ' | '\t' | '\n' | '*' | '?' | '[' | '{' | '`' | '$' | '\\'
+            | '%' | '#' | '\'' | '"' | '|' | '!' | '<' => {
+                escaped.push('\\');
+            }
+            '+' | '>' if i == 0 => {
+                escaped.push('\\');
+            }
+            _ => {}
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Escapes `path` to be used as an item of 'runtimepath' inside a single
+/// quoted vimscript string.
+pub fn escape_rtp_item(path: &str) -> String {
+    path.replace(',', "\\,").replace('\'', "''")
+}
+
+/// Returns `path` for showing to the user, with `home` shortened to `~`.
+pub fn display_path(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => String::from("~"),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
 
+    #[test]
+    fn test_escape_filename() {
+        assert_eq!(escape_filename("foo.rs"), "foo.rs");
+        assert_eq!(escape_filename("my file.rs"), "my\\ file.rs");
+        assert_eq!(escape_filename("#1%.txt"), "\\#1\\%.txt");
+        assert_eq!(escape_filename("+cmd"), "\\+cmd");
+        assert_eq!(escape_filename("a+b"), "a+b");
+    }
+
+    #[test]
+    fn test_escape_rtp_item() {
+        assert_eq!(escape_rtp_item("/usr/share/gnvim"), "/usr/share/gnvim");
+        assert_eq!(escape_rtp_item("/tmp/it's"), "/tmp/it''s");
+        assert_eq!(escape_rtp_item("/tmp/a,b"), "/tmp/a\\,b");
+    }
+
+    #[test]
+    fn test_display_path() {
+        let home = Some(Path::new("/home/foo"));
+        assert_eq!(display_path(Path::new("/home/foo/src"), home), "~/src");
+        assert_eq!(display_path(Path::new("/home/foo"), home), "~");
+        assert_eq!(
+            display_path(Path::new("/home/foobar"), home),
+            "/home/foobar"
+        );
+        assert_eq!(display_path(Path::new("/tmp"), None), "/tmp");
+    }
+
     #[test]
     fn test_calc_line_space() {
         assert_eq!((1, 0), calc_line_space(1));
@@ -0,0 +1,57 @@
+use gtk::prelude::*;
+
+/// Shows an error dialog. The dialog is added to `app`, so it keeps the
+/// application alive even if there are no other windows open.
+///
+/// * `app` - GTK application.
+/// * `parent` - Window to attach the dialog to, if any.
+/// * `message` - The error itself.
+/// * `details` - Additional (possibly long) information, such as nvim's
+///               stderr. Shown in a scrollable, collapsed area if not empty.
+pub fn show_error(
+    app: &gtk::Application,
+    parent: Option<&gtk::Window>,
+    message: &str,
+    details: &str,
+) {
+    let dialog = gtk::MessageDialog::new(
+        parent,
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Close,
+        message,
+    );
+    dialog.set_title("Gnvim error");
+    dialog.set_application(Some(app));
+
+    if !details.is_empty() {
+        let buffer = gtk::TextBuffer::new::<gtk::TextTagTable>(None);
+        buffer.set_text(details);
+
+        let view = gtk::TextView::with_buffer(&buffer);
+        view.set_editable(false);
+        view.set_monospace(true);
+        view.set_wrap_mode(gtk::WrapMode::WordChar);
+
+        let scroll = gtk::ScrolledWindow::new(
+            None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>,
+        );
+        scroll.set_min_content_height(200);
+        scroll.set_min_content_width(500);
+        scroll.add(&view);
+
+        let expander = gtk::Expander::new(Some("Nvim's output"));
+        expander.add(&scroll);
+
+        if let Some(area) = dialog
+            .get_message_area()
+            .and_then(|w| w.downcast::<gtk::Box>().ok())
+        {
+            area.pack_start(&expander, true, true, 0);
+        }
+    }
+
+    dialog.connect_response(|dialog, _| dialog.close());
+    dialog.show_all();
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 
 const DEFAULT_HEIGHT: f32 = 14.0;
 
@@ -19,48 +20,57 @@ impl Display for FontUnit {
 
 #[derive(Clone, Debug)]
 pub struct Font {
-    name: String,
+    /// Font families, in the order of preference. The later ones are
+    /// fallbacks for characters that the first one doesn't have.
+    families: Vec<String>,
     pub height: f32,
 }
 
 impl Font {
-    /// Parses nvim `guifont` option.
+    /// Parses nvim `guifont` option. Multiple comma separated fonts (e.g.
+    /// `Iosevka,Noto Color Emoji:h12`) are used as a fallback chain. The
+    /// height can be given after any of them.
     ///
     /// If invalid height is specified, defaults to `DEFAULT_HEIGHT`.
     pub fn from_guifont(guifont: &str) -> Result<Self, ()> {
-        let mut parts = guifont.split(':');
-
-        let name = parts.next().ok_or(())?;
-
-        if name.is_empty() {
-            return Err(());
-        }
-
         let mut font = Font {
-            name: name.to_string(),
+            families: vec![],
             height: DEFAULT_HEIGHT,
         };
 
-        for part in parts {
-            let mut chars = part.chars();
-            if let Some(ch) = chars.next() {
-                match ch {
-                    'h' => {
-                        let rest = chars.collect::<String>();
-                        let h = rest.parse::<f32>().or(Err(()))?;
-                        if h <= 0.0 {
-                            // Ignore zero sized font.
-                            continue;
+        for entry in guifont.split(',') {
+            let mut parts = entry.split(':');
+
+            let name = parts.next().ok_or(())?.trim();
+            if !name.is_empty() {
+                font.families.push(name.to_string());
+            }
+
+            for part in parts {
+                let mut chars = part.chars();
+                if let Some(ch) = chars.next() {
+                    match ch {
+                        'h' => {
+                            let rest = chars.collect::<String>();
+                            let h = rest.parse::<f32>().or(Err(()))?;
+                            if h <= 0.0 {
+                                // Ignore zero sized font.
+                                continue;
+                            }
+                            font.height = h;
+                        }
+                        _ => {
+                            println!("Not supported guifont option: {}", part);
                         }
-                        font.height = h;
-                    }
-                    _ => {
-                        println!("Not supported guifont option: {}", part);
                     }
                 }
             }
         }
 
+        if font.families.is_empty() {
+            return Err(());
+        }
+
         Ok(font)
     }
 
@@ -74,17 +84,27 @@ impl Font {
              font-family: \"{font_family}\"; \
              font-size: {font_size}{font_unit}; \
              }}",
-            font_family = self.name,
+            font_family = self.families.join("\", \""),
             font_size = self.height,
             font_unit = unit,
         )
     }
 
+    /// Returns pango attributes that switch the font to self.
+    pub fn as_pango_attrs(&self) -> Vec<pango::Attribute> {
+        let size = (self.height * pango::SCALE as f32) as i32;
+        vec![
+            pango::Attribute::new_family(&self.families.join(",")).unwrap(),
+            pango::Attribute::new_size(size).unwrap(),
+        ]
+    }
+
     /// Returns a pango::FontDescription version of self.
     pub fn as_pango_font(&self) -> pango::FontDescription {
         let mut font_desc = pango::FontDescription::from_string(&format!(
             "{} {}",
-            self.name, self.height
+            self.families.join(","),
+            self.height
         ));
 
         // Make sure we dont have a font with size of 0, otherwise we'll
@@ -97,10 +117,28 @@ impl Font {
     }
 }
 
+// f32 isn't Eq nor Hash, so the height is compared bitwise. Good enough for
+// telling fonts apart (e.g. in the glyph cache).
+impl PartialEq for Font {
+    fn eq(&self, other: &Self) -> bool {
+        self.families == other.families
+            && self.height.to_bits() == other.height.to_bits()
+    }
+}
+
+impl Eq for Font {}
+
+impl Hash for Font {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.families.hash(state);
+        self.height.to_bits().hash(state);
+    }
+}
+
 impl Default for Font {
     fn default() -> Self {
         Font {
-            name: String::from("Monospace"),
+            families: vec![String::from("Monospace")],
             height: DEFAULT_HEIGHT,
         }
     }
@@ -113,7 +151,7 @@ mod tests {
     #[test]
     fn test_as_wild_css() {
         let font = Font {
-            name: "foo".to_string(),
+            families: vec!["foo".to_string()],
             height: 10.0,
         };
 
@@ -138,7 +176,7 @@ mod tests {
     fn test_from_guifont() {
         // Font with proper height.
         let f = Font::from_guifont("monospace:h11").unwrap();
-        assert_eq!(f.name, "monospace");
+        assert_eq!(f.families, vec!["monospace"]);
         assert_eq!(f.height, 11.0);
 
         // Font with invalid height.
@@ -149,17 +187,54 @@ mod tests {
 
         // Font with height zero.
         let f = Font::from_guifont("foo:h0").unwrap();
-        assert_eq!(f.name, "foo");
+        assert_eq!(f.families, vec!["foo"]);
         assert_eq!(f.height, DEFAULT_HEIGHT);
 
         // Font with negative height.
         let f = Font::from_guifont("font:h-1").unwrap();
-        assert_eq!(f.name, "font");
+        assert_eq!(f.families, vec!["font"]);
         assert_eq!(f.height, DEFAULT_HEIGHT);
 
         // Font with no height.
         let f = Font::from_guifont("bar").unwrap();
-        assert_eq!(f.name, "bar");
+        assert_eq!(f.families, vec!["bar"]);
         assert_eq!(f.height, DEFAULT_HEIGHT);
     }
+
+    #[test]
+    fn test_from_guifont_fallbacks() {
+        let f = Font::from_guifont(
+            "Iosevka,Noto Color Emoji,Symbols Nerd Font:h12",
+        )
+        .unwrap();
+        assert_eq!(
+            f.families,
+            vec!["Iosevka", "Noto Color Emoji", "Symbols Nerd Font"]
+        );
+        assert_eq!(f.height, 12.0);
+
+        // Height after the first font.
+        let f = Font::from_guifont("foo:h10, bar").unwrap();
+        assert_eq!(f.families, vec!["foo", "bar"]);
+        assert_eq!(f.height, 10.0);
+
+        // No font at all.
+        assert_eq!(Font::from_guifont(",:h10").is_err(), true);
+    }
+
+    #[test]
+    fn test_as_wild_css_fallbacks() {
+        let font = Font {
+            families: vec!["foo".to_string(), "bar".to_string()],
+            height: 10.0,
+        };
+
+        assert_eq!(
+            font.as_wild_css(FontUnit::Point),
+            "* { \
+             font-family: \"foo\", \"bar\"; \
+             font-size: 10pt; \
+             }"
+        );
+    }
 }
@@ -6,6 +6,7 @@ use crate::ui::font::Font;
 use crate::ui::grid::cursor::Cursor;
 use crate::ui::grid::render;
 use crate::ui::grid::row::{Cell, Row};
+use crate::ui::grid::selection::Selection;
 
 /// Context is manipulated by Grid.
 pub struct Context {
@@ -32,6 +33,12 @@ pub struct Context {
 
     /// Areas to call queue_draw_area on the drawing area on flush.
     pub queue_draw_area: Vec<(f64, f64, f64, f64)>,
+
+    /// GUI-local selection, when nvim's mouse is disabled.
+    pub selection: Option<Selection>,
+
+    /// If the grid is flashing for the visual bell.
+    pub flash: bool,
 }
 
 impl Context {
@@ -46,8 +53,58 @@ impl Context {
         hl_defs: &HlDefs,
         enable_cursor_animations: bool,
     ) -> Self {
-        let pango_context = da.get_pango_context();
+        Context::new_with(
+            &da.get_pango_context(),
+            font,
+            line_space,
+            cols,
+            rows,
+            hl_defs,
+            enable_cursor_animations,
+            |content, w, h| win.create_similar_surface(content, w, h).unwrap(),
+        )
+    }
+
+    /// Creates a context that renders to image surfaces, so no display is
+    /// needed. Cursor animations are disabled.
+    pub fn new_headless(
+        pango_context: &pango::Context,
+        font: Font,
+        line_space: i64,
+        cols: usize,
+        rows: usize,
+        hl_defs: &HlDefs,
+    ) -> Self {
+        Context::new_with(
+            pango_context,
+            font,
+            line_space,
+            cols,
+            rows,
+            hl_defs,
+            false,
+            create_image_surface,
+        )
+    }
 
+    /// Creates new context.
+    ///
+    /// * `create_surface` - Creates the surfaces to draw to. Params are
+    ///                      content, width and height.
+    #[allow(clippy::too_many_arguments)]
+    fn new_with<F>(
+        pango_context: &pango::Context,
+        font: Font,
+        line_space: i64,
+        cols: usize,
+        rows: usize,
+        hl_defs: &HlDefs,
+        enable_cursor_animations: bool,
+        create_surface: F,
+    ) -> Self
+    where
+        F: Fn(cairo::Content, i32, i32) -> cairo::Surface,
+    {
         let font_desc = font.as_pango_font();
         pango_context.set_font_description(&font_desc);
 
@@ -58,13 +115,11 @@ impl Context {
 
         let w = cell_metrics.width * cols as f64;
         let h = cell_metrics.height * rows as f64;
-        let surface = win
-            .create_similar_surface(
-                cairo::Content::Color,
-                w.ceil() as i32,
-                h.ceil() as i32,
-            )
-            .unwrap();
+        let surface = create_surface(
+            cairo::Content::Color,
+            w.ceil() as i32,
+            h.ceil() as i32,
+        );
 
         let cairo_context = cairo::Context::new(&surface);
 
@@ -79,13 +134,11 @@ impl Context {
         cairo_context.restore();
 
         let cursor_context = {
-            let surface = win
-                .create_similar_surface(
-                    cairo::Content::ColorAlpha,
-                    (cell_metrics.width * 2.0) as i32, // times two for double width chars.
-                    (cell_metrics.height + cell_metrics.ascent).ceil() as i32,
-                )
-                .unwrap();
+            let surface = create_surface(
+                cairo::Content::ColorAlpha,
+                (cell_metrics.width * 2.0) as i32, // times two for double width chars.
+                (cell_metrics.height + cell_metrics.ascent).ceil() as i32,
+            );
             cairo::Context::new(&surface)
         };
 
@@ -107,6 +160,9 @@ impl Context {
             active: false,
 
             queue_draw_area: vec![],
+
+            selection: None,
+            flash: false,
         }
     }
 
@@ -119,6 +175,42 @@ impl Context {
         rows: usize,
         hl_defs: &HlDefs,
     ) {
+        self.resize_with(
+            &da.get_pango_context(),
+            cols,
+            rows,
+            hl_defs,
+            |content, w, h| win.create_similar_surface(content, w, h).unwrap(),
+        );
+    }
+
+    /// Headless version of `resize` (see `new_headless`).
+    pub fn resize_headless(
+        &mut self,
+        pango_context: &pango::Context,
+        cols: usize,
+        rows: usize,
+        hl_defs: &HlDefs,
+    ) {
+        self.resize_with(
+            pango_context,
+            cols,
+            rows,
+            hl_defs,
+            create_image_surface,
+        );
+    }
+
+    fn resize_with<F>(
+        &mut self,
+        pctx: &pango::Context,
+        cols: usize,
+        rows: usize,
+        hl_defs: &HlDefs,
+        create_surface: F,
+    ) where
+        F: Fn(cairo::Content, i32, i32) -> cairo::Surface,
+    {
         let prev_rows = self.rows.len();
         let prev_cols = self.rows.get(0).map(|r| r.len()).unwrap_or(0);
 
@@ -132,20 +224,17 @@ impl Context {
             }
         }
 
-        let pctx = da.get_pango_context();
         pctx.set_font_description(&self.cell_metrics.font.as_pango_font());
 
         self.cell_metrics.update(&pctx);
 
         let w = self.cell_metrics.width * cols as f64;
         let h = self.cell_metrics.height * rows as f64;
-        let surface = win
-            .create_similar_surface(
-                cairo::Content::Color,
-                w.ceil() as i32,
-                h.ceil() as i32,
-            )
-            .unwrap();
+        let surface = create_surface(
+            cairo::Content::Color,
+            w.ceil() as i32,
+            h.ceil() as i32,
+        );
         let ctx = cairo::Context::new(&surface);
 
         // Fill the context with default bg color.
@@ -294,13 +383,35 @@ impl Context {
         da.queue_draw_area(x, y, w, h);
     }
 
-    pub fn cell_at_cursor(&self) -> Option<&Cell> {
+    /// Returns the row the cursor is on, and the cursor's column.
+    pub fn row_at_cursor(&self) -> Option<(&Row, usize)> {
         self.cursor.get_position().and_then(|pos| {
             self.rows
                 .get(pos.0.ceil() as usize)
-                .and_then(|row| row.cell_at(pos.1.ceil() as usize))
+                .map(|row| (row, pos.1.ceil() as usize))
         })
     }
+
+    pub fn cell_at_cursor(&self) -> Option<&Cell> {
+        self.row_at_cursor().and_then(|(row, col)| row.cell_at(col))
+    }
+}
+
+/// Creates an image surface to be used instead of a surface that is
+/// similar to a window's surface.
+fn create_image_surface(
+    content: cairo::Content,
+    width: i32,
+    height: i32,
+) -> cairo::Surface {
+    let format = match content {
+        cairo::Content::Color => cairo::Format::Rgb24,
+        _ => cairo::Format::ARgb32,
+    };
+
+    let surface = cairo::ImageSurface::create(format, width, height)
+        .expect("Failed to create image surface");
+    (*surface).clone()
 }
 
 /// Cell metrics tells the size (and other metrics) of the cells in a grid.
@@ -312,9 +423,14 @@ pub struct CellMetrics {
     pub decent: f64,
     pub underline_thickness: f64,
     pub underline_position: f64,
+    pub strikethrough_thickness: f64,
+    /// Distance from the baseline to the top of the strikethrough.
+    pub strikethrough_position: f64,
 
     pub line_space: i64,
     pub font: Font,
+    /// Font for double width cells (`'guifontwide'`).
+    pub font_wide: Option<Font>,
 }
 
 impl CellMetrics {
@@ -334,5 +450,10 @@ impl CellMetrics {
         // TODO(ville): make the underline thickness a bit thicker (one 10th of the cell height?).
         self.underline_thickness =
             f64::from(fm.get_underline_thickness()) / scale * 2.0;
+
+        self.strikethrough_position =
+            f64::from(fm.get_strikethrough_position()) / scale;
+        self.strikethrough_thickness =
+            f64::from(fm.get_strikethrough_thickness()) / scale;
     }
 }
@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+
+use lru::LruCache;
+
+/// How many shaped texts are kept around.
+const CAPACITY: usize = 4096;
+
+/// Identifies a shaped text. Besides the text itself, the result of shaping
+/// depends on the font variant, the cells taken by each char and whether
+/// the wide font is used. The fonts and the cell metrics aren't part of the
+/// key; the cache is cleared whenever they change.
+#[derive(PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    text: String,
+    cells: Vec<usize>,
+    bold: bool,
+    italic: bool,
+    wide: bool,
+}
+
+impl GlyphKey {
+    pub fn new(
+        text: &str,
+        cells: &[usize],
+        bold: bool,
+        italic: bool,
+        wide: bool,
+    ) -> Self {
+        GlyphKey {
+            text: text.to_string(),
+            cells: cells.to_vec(),
+            bold,
+            italic,
+            wide,
+        }
+    }
+}
+
+/// Glyphs of a single pango item.
+pub struct ShapedItem {
+    pub font: pango::Font,
+    pub glyphs: pango::GlyphString,
+    /// How many cells the item takes.
+    pub cells: usize,
+    /// Offset and scale that fit the glyphs into their cells.
+    pub x_offset: f64,
+    pub scale: f64,
+}
+
+thread_local! {
+    /// Shaped texts of all the grids. Grids are only used on the main thread,
+    /// so that's where the cache lives too.
+    static CACHE: RefCell<LruCache<GlyphKey, Vec<ShapedItem>>> =
+        RefCell::new(LruCache::new(CAPACITY));
+}
+
+/// Calls `f` with the shaped text of `key`. If the text isn't in the cache,
+/// it is shaped with `shape` and cached.
+pub fn with_shaped<S, F>(key: GlyphKey, shape: S, f: F)
+where
+    S: FnOnce() -> Vec<ShapedItem>,
+    F: FnOnce(&mut [ShapedItem]),
+{
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(items) = cache.get_mut(&key) {
+            f(items);
+            return;
+        }
+
+        let mut items = shape();
+        f(&mut items);
+        cache.put(key, items);
+    });
+}
+
+/// Drops everything from the cache. Needs to be called when the font or
+/// the cell metrics change.
+pub fn clear() {
+    CACHE.with(|cache| cache.borrow_mut().clear());
+}
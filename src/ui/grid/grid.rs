@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::fmt::Display;
 use std::rc::Rc;
@@ -12,7 +12,12 @@ use crate::nvim_bridge::{GridLineSegment, ModeInfo};
 use crate::ui::color::HlDefs;
 use crate::ui::font::Font;
 use crate::ui::grid::context::Context;
+use crate::ui::grid::glyph_cache;
 use crate::ui::grid::render;
+use crate::ui::grid::selection::Selection;
+
+/// How long (in ms) the visual bell flashes the grid.
+const FLASH_DURATION: u32 = 100;
 
 pub struct GridMetrics {
     // Row count in the grid.
@@ -75,6 +80,14 @@ pub struct Grid {
     drag_position: Rc<RefCell<(u64, u64)>>,
     /// Input context that need to be updated for the cursor position
     im_context: Option<gtk::IMMulticontext>,
+    /// If nvim's mouse support is enabled (`mouse_on`/`mouse_off`). When
+    /// it is not, mouse buttons are used for selecting text in the grid
+    /// instead of sending them to nvim.
+    mouse_enabled: Rc<Cell<bool>>,
+    /// Cell where the left button was pressed, while the mouse is used for
+    /// selecting text. The selection starts there once the pointer moves to
+    /// another cell.
+    selection_start: Rc<Cell<Option<(u64, u64)>>>,
 }
 
 impl Grid {
@@ -124,6 +137,8 @@ impl Grid {
             context: ctx,
             drag_position: Rc::new(RefCell::new((0, 0))),
             im_context: None,
+            mouse_enabled: Rc::new(Cell::new(true)),
+            selection_start: Rc::new(Cell::new(None)),
         }
     }
 
@@ -134,19 +149,22 @@ impl Grid {
     pub fn flush(&self, hl_defs: &HlDefs) {
         let mut ctx = self.context.borrow_mut();
 
-        if let Some(cell) = ctx.cell_at_cursor() {
+        if let Some((row, col)) = ctx.row_at_cursor() {
             // If cursor isn't blinking, drawn the inverted cell into
             // the cursor's cairo context.
             if ctx.cursor.blink_on == 0 {
                 render::cursor_cell(
                     &ctx.cursor_context,
                     &self.da.get_pango_context(),
-                    &cell,
+                    row,
+                    col,
                     &ctx.cell_metrics,
                     hl_defs,
                 );
             }
+        }
 
+        if let Some(cell) = ctx.cell_at_cursor() {
             // Update cursor color.
             let hl = hl_defs.get(&cell.hl_id).unwrap();
             ctx.cursor.color = hl.foreground.unwrap_or(hl_defs.default_fg);
@@ -228,9 +246,12 @@ impl Grid {
     {
         let ctx = self.context.clone();
         let drag_position = self.drag_position.clone();
+        let mouse_enabled = self.mouse_enabled.clone();
+        let selection_start = self.selection_start.clone();
+        let da = self.da.clone();
 
         self.eb.connect_motion_notify_event(move |_, e| {
-            let ctx = ctx.borrow();
+            let mut ctx = ctx.borrow_mut();
             let mut drag_position = drag_position.borrow_mut();
 
             let button = match e.get_state() {
@@ -245,6 +266,22 @@ impl Grid {
 
             if drag_position.0 != col || drag_position.1 != row {
                 *drag_position = (col, row);
+
+                if !mouse_enabled.get() {
+                    // Only dragging with the left button selects.
+                    let start = selection_start.get().filter(|_| {
+                        e.get_state().contains(ModifierType::BUTTON1_MASK)
+                    });
+                    if let Some(start) = start {
+                        if ctx.selection.is_some() || start != (row, col) {
+                            ctx.selection =
+                                Some(Selection::new(start, (row, col)));
+                            da.queue_draw();
+                        }
+                    }
+                    return Inhibit(false);
+                }
+
                 f(button, row, col)
             } else {
                 Inhibit(false)
@@ -259,9 +296,13 @@ impl Grid {
         F: Fn(MouseButton, u64, u64) -> Inhibit,
     {
         let ctx = self.context.clone();
+        let mouse_enabled = self.mouse_enabled.clone();
+        let selection_start = self.selection_start.clone();
+        let da = self.da.clone();
+        let copy_menu = copy_menu(&self.da, &self.context);
 
         self.eb.connect_button_press_event(move |_, e| {
-            let ctx = ctx.borrow();
+            let mut ctx = ctx.borrow_mut();
 
             let button = match e.get_button() {
                 3 => MouseButton::Right,
@@ -273,6 +314,27 @@ impl Grid {
             let col = (pos.0 / ctx.cell_metrics.width).floor() as u64;
             let row = (pos.1 / ctx.cell_metrics.height).floor() as u64;
 
+            if !mouse_enabled.get() {
+                match button {
+                    // Right click offers to copy the selection.
+                    MouseButton::Right if ctx.selection.is_some() => {
+                        copy_menu.popup_easy(e.get_button(), e.get_time());
+                    }
+                    // The selection starts once the pointer is dragged, so
+                    // a plain click just clears the old one.
+                    button => {
+                        selection_start.set(match button {
+                            MouseButton::Left => Some((row, col)),
+                            _ => None,
+                        });
+                        if ctx.selection.take().is_some() {
+                            da.queue_draw();
+                        }
+                    }
+                }
+                return Inhibit(false);
+            }
+
             f(button, row, col)
         });
     }
@@ -284,6 +346,8 @@ impl Grid {
         F: Fn(MouseButton, u64, u64) -> Inhibit,
     {
         let ctx = self.context.clone();
+        let mouse_enabled = self.mouse_enabled.clone();
+        let selection_start = self.selection_start.clone();
 
         self.eb.connect_button_release_event(move |_, e| {
             let ctx = ctx.borrow();
@@ -298,6 +362,19 @@ impl Grid {
             let col = (pos.0 / ctx.cell_metrics.width).floor() as u64;
             let row = (pos.1 / ctx.cell_metrics.height).floor() as u64;
 
+            if !mouse_enabled.get() {
+                if let MouseButton::Left = button {
+                    selection_start.set(None);
+                    if let Some(ref selection) = ctx.selection {
+                        copy_to_clipboard(
+                            &selection.text(&ctx.rows),
+                            &gdk::SELECTION_PRIMARY,
+                        );
+                    }
+                }
+                return Inhibit(false);
+            }
+
             f(button, row, col)
         });
     }
@@ -324,7 +401,20 @@ impl Grid {
     pub fn put_line(&self, line: GridLineSegment, hl_defs: &HlDefs) {
         let mut ctx = self.context.borrow_mut();
 
+        // Nvim redraws lines that didn't change too, so the selection is only
+        // cleared if its text changes.
+        let row = line.row;
+        let selected = ctx
+            .selection
+            .and_then(|selection| selection.row_text(&ctx.rows, row));
+
         render::put_line(&mut ctx, &self.da.get_pango_context(), line, hl_defs);
+
+        if let (Some(selected), Some(selection)) = (selected, ctx.selection) {
+            if selection.row_text(&ctx.rows, row).as_ref() != Some(&selected) {
+                self.clear_selection(&mut ctx);
+            }
+        }
     }
 
     pub fn redraw(&self, hl_defs: &HlDefs) {
@@ -349,6 +439,17 @@ impl Grid {
         }
     }
 
+    /// Highlight id of the cell under the cursor.
+    pub fn cursor_hl_id(&self) -> Option<u64> {
+        let ctx = self.context.borrow();
+        let (row, col) = ctx.cursor.get_position()?;
+
+        ctx.rows
+            .get(row as usize)?
+            .cell_at(col as usize)
+            .map(|cell| cell.hl_id)
+    }
+
     pub fn get_grid_metrics(&self) -> GridMetrics {
         let ctx = self.context.borrow();
 
@@ -390,18 +491,25 @@ impl Grid {
         hl_defs: &HlDefs,
     ) {
         let mut ctx = self.context.borrow_mut();
+        self.clear_selection(&mut ctx);
         ctx.resize(&self.da, win, cols as usize, rows as usize, hl_defs);
     }
 
     pub fn clear(&self, hl_defs: &HlDefs) {
         let mut ctx = self.context.borrow_mut();
+        self.clear_selection(&mut ctx);
 
         // Clear internal grid (rows).
         for row in ctx.rows.iter_mut() {
             row.clear();
         }
 
-        render::clear(&self.da, &mut ctx, hl_defs)
+        render::clear(
+            &mut ctx,
+            hl_defs,
+            self.da.get_allocated_width(),
+            self.da.get_allocated_height(),
+        )
     }
 
     pub fn scroll(
@@ -413,6 +521,13 @@ impl Grid {
     ) {
         let mut ctx = self.context.borrow_mut();
 
+        // Keep the selection on the text it was on.
+        if let Some(selection) = ctx.selection {
+            let cols = ctx.rows.get(0).map(|row| row.len).unwrap_or(0) as u64;
+            ctx.selection = selection.scroll(reg, rows, cols);
+            self.da.queue_draw();
+        }
+
         render::scroll(&mut ctx, hl_defs, reg, rows);
     }
 
@@ -432,6 +547,7 @@ impl Grid {
     ) {
         let mut ctx = self.context.borrow_mut();
         ctx.update_metrics(font, line_space, &self.da, win);
+        glyph_cache::clear();
     }
 
     /// Get the current line space value.
@@ -446,6 +562,14 @@ impl Grid {
         ctx.cell_metrics.font.clone()
     }
 
+    /// Sets the font for double width characters. Without it, the normal
+    /// font is used.
+    pub fn set_font_wide(&self, font: Option<Font>) {
+        let mut ctx = self.context.borrow_mut();
+        ctx.cell_metrics.font_wide = font;
+        glyph_cache::clear();
+    }
+
     pub fn set_mode(&self, mode: &ModeInfo) {
         let mut ctx = self.context.borrow_mut();
 
@@ -463,11 +587,84 @@ impl Grid {
         let mut ctx = self.context.borrow_mut();
         ctx.cursor.disable_animation = !enable;
     }
+
+    /// Briefly flashes the grid, for the visual bell.
+    pub fn flash(&self) {
+        let mut ctx = self.context.borrow_mut();
+        if ctx.flash {
+            return;
+        }
+
+        ctx.flash = true;
+        self.da.queue_draw();
+
+        let da = self.da.downgrade();
+        let ctx = Rc::downgrade(&self.context);
+        gtk::timeout_add(FLASH_DURATION, move || {
+            if let Some(ctx) = ctx.upgrade() {
+                ctx.borrow_mut().flash = false;
+            }
+            if let Some(da) = da.upgrade() {
+                da.queue_draw();
+            }
+
+            glib::Continue(false)
+        });
+    }
+
+    /// Drops the selection, e.g. when the text under it changes.
+    fn clear_selection(&self, ctx: &mut Context) {
+        if ctx.selection.take().is_some() {
+            self.da.queue_draw();
+        }
+    }
+
+    /// Sets if mouse events should be sent to nvim. If not, the mouse is
+    /// used to select (and copy) text in the grid.
+    pub fn set_mouse_enabled(&self, enabled: bool) {
+        self.mouse_enabled.set(enabled);
+
+        if enabled {
+            self.selection_start.set(None);
+            self.clear_selection(&mut self.context.borrow_mut());
+        }
+    }
+}
+
+/// Puts `text` to `selection` (the clipboard or the primary selection).
+fn copy_to_clipboard(text: &str, selection: &gdk::Atom) {
+    if text.is_empty() {
+        return;
+    }
+
+    gtk::Clipboard::get(selection).set_text(text);
+}
+
+/// Creates a context menu for `da` that copies the selection of `ctx` to the
+/// clipboard. Mouse selections only go to the primary selection on their own.
+fn copy_menu(da: &DrawingArea, ctx: &Rc<RefCell<Context>>) -> gtk::Menu {
+    let menu = gtk::Menu::new();
+    menu.set_property_attach_widget(Some(da));
+
+    let copy = gtk::MenuItem::with_label("Copy");
+    copy.connect_activate(clone!(ctx => move |_| {
+        let ctx = ctx.borrow();
+        if let Some(ref selection) = ctx.selection {
+            copy_to_clipboard(
+                &selection.text(&ctx.rows),
+                &gdk::SELECTION_CLIPBOARD,
+            );
+        }
+    }));
+    menu.append(&copy);
+    menu.show_all();
+
+    menu
 }
 
 /// Handler for grid's drawingarea's draw event. Draws the internal cairo
 /// context (`ctx`) surface to the `cr`.
-fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
+pub(super) fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
     let surface = ctx.cairo_context.get_target();
     surface.flush();
 
@@ -476,6 +673,26 @@ fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
     cr.paint();
     cr.restore();
 
+    // Invert the colors of the selected cells.
+    if let Some(ref selection) = ctx.selection {
+        let cm = &ctx.cell_metrics;
+        let cols = ctx.rows.get(0).map(|row| row.len).unwrap_or(0) as u64;
+
+        cr.save();
+        cr.set_operator(cairo::Operator::Difference);
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        for (row, start, end) in selection.row_ranges(cols) {
+            cr.rectangle(
+                start as f64 * cm.width,
+                row as f64 * cm.height,
+                (end - start) as f64 * cm.width,
+                cm.height,
+            );
+        }
+        cr.fill();
+        cr.restore();
+    }
+
     // If we're not "busy", draw the cursor.
     if !ctx.busy && ctx.active {
         let (x, y, w, h) = ctx.get_cursor_rect();
@@ -493,4 +710,13 @@ fn drawingarea_draw(cr: &cairo::Context, ctx: &mut Context) {
         cr.fill();
         cr.restore();
     }
+
+    // Visual bell.
+    if ctx.flash {
+        cr.save();
+        cr.set_operator(cairo::Operator::Difference);
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.paint();
+        cr.restore();
+    }
 }
@@ -0,0 +1,437 @@
+use std::io;
+
+use crate::nvim_bridge::{
+    DefaultColorsSet, GridCursorGoto, GridResize, ModeInfo, OptionSet,
+    RedrawEvent,
+};
+use crate::ui::color::{Highlight, HlDefs};
+use crate::ui::font::Font;
+use crate::ui::grid::context::Context;
+use crate::ui::grid::glyph_cache;
+use crate::ui::grid::grid::drawingarea_draw;
+use crate::ui::grid::render;
+
+/// Renders a single grid without a display, using the same code paths as
+/// `Grid`. Useful for (golden image) tests and for turning recordings into
+/// images.
+pub struct HeadlessGrid {
+    /// Id of the grid we're rendering. Events for other grids are ignored.
+    id: i64,
+    hl_defs: HlDefs,
+    /// Pango context that is not tied to any widget.
+    pango_context: pango::Context,
+    /// Created on the first `grid_resize` event.
+    context: Option<Context>,
+
+    font: Font,
+    font_wide: Option<Font>,
+    line_space: i64,
+    mode_infos: Vec<ModeInfo>,
+    current_mode: Option<ModeInfo>,
+}
+
+impl HeadlessGrid {
+    pub fn new(id: i64, font: Font, line_space: i64) -> Self {
+        let surface = cairo::ImageSurface::create(cairo::Format::Rgb24, 1, 1)
+            .expect("Failed to create image surface");
+        let cr = cairo::Context::new(&surface);
+        let pango_context = pangocairo::functions::create_context(&cr)
+            .expect("Failed to create pango context");
+
+        let mut hl_defs = HlDefs::default();
+        hl_defs.insert(0, Highlight::default());
+
+        HeadlessGrid {
+            id,
+            hl_defs,
+            pango_context,
+            context: None,
+            font,
+            font_wide: None,
+            line_space,
+            mode_infos: vec![],
+            current_mode: None,
+        }
+    }
+
+    pub fn handle_redraw_event(&mut self, event: RedrawEvent) {
+        match event {
+            RedrawEvent::GridResize(evt) => {
+                evt.into_iter().for_each(|e| self.grid_resize(e))
+            }
+            RedrawEvent::GridLine(evt) => {
+                for line in evt.into_iter().filter(|l| l.grid == self.id) {
+                    if let Some(ref mut ctx) = self.context {
+                        render::put_line(
+                            ctx,
+                            &self.pango_context,
+                            line,
+                            &self.hl_defs,
+                        );
+                    }
+                }
+            }
+            RedrawEvent::GridClear(evt) => {
+                if evt.contains(&self.id) {
+                    self.clear();
+                }
+            }
+            RedrawEvent::GridScroll(evt) => {
+                for e in evt.into_iter().filter(|e| e.grid == self.id) {
+                    if let Some(ref mut ctx) = self.context {
+                        render::scroll(ctx, &self.hl_defs, e.reg, e.rows);
+                    }
+                }
+            }
+            RedrawEvent::GridCursorGoto(evt) => {
+                evt.into_iter().for_each(|e| self.cursor_goto(e))
+            }
+            RedrawEvent::DefaultColorsSet(evt) => {
+                evt.into_iter().for_each(|e| self.default_colors_set(e))
+            }
+            RedrawEvent::HlAttrDefine(evt) => {
+                for e in evt {
+                    self.hl_defs.insert(e.id, e.hl);
+                    self.hl_defs.insert_info(e.id, e.info);
+                }
+            }
+            RedrawEvent::OptionSet(evt) => {
+                evt.into_iter().for_each(|e| self.option_set(e))
+            }
+            RedrawEvent::ModeInfoSet(evt) => {
+                for e in evt {
+                    self.mode_infos = e.mode_info;
+                }
+            }
+            RedrawEvent::ModeChange(evt) => {
+                for e in evt {
+                    self.current_mode =
+                        self.mode_infos.get(e.index as usize).cloned();
+                    self.set_mode();
+                }
+            }
+            // There is no widget to queue the drawing for.
+            RedrawEvent::Flush() => {
+                if let Some(ref mut ctx) = self.context {
+                    ctx.queue_draw_area.clear();
+                }
+            }
+            RedrawEvent::SetBusy(busy) => {
+                if let Some(ref mut ctx) = self.context {
+                    ctx.busy = busy;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn grid_resize(&mut self, e: GridResize) {
+        if e.grid != self.id {
+            return;
+        }
+
+        let (cols, rows) = (e.width as usize, e.height as usize);
+        if let Some(ref mut ctx) = self.context {
+            ctx.resize_headless(&self.pango_context, cols, rows, &self.hl_defs);
+            return;
+        }
+
+        let mut ctx = Context::new_headless(
+            &self.pango_context,
+            self.font.clone(),
+            self.line_space,
+            cols,
+            rows,
+            &self.hl_defs,
+        );
+        ctx.cell_metrics.font_wide = self.font_wide.clone();
+        ctx.resize_headless(&self.pango_context, cols, rows, &self.hl_defs);
+        // Before we get any mode info, draw a block cursor.
+        ctx.cursor.cell_percentage = 1.0;
+
+        self.context = Some(ctx);
+        self.set_mode();
+    }
+
+    fn clear(&mut self) {
+        if let Some(ref mut ctx) = self.context {
+            for row in ctx.rows.iter_mut() {
+                row.clear();
+            }
+
+            let (w, h) = surface_size(ctx);
+            render::clear(ctx, &self.hl_defs, w, h);
+        }
+    }
+
+    fn cursor_goto(&mut self, e: GridCursorGoto) {
+        if let Some(ref mut ctx) = self.context {
+            ctx.active = e.grid == self.id;
+            if ctx.active {
+                // No animations, so the frame time doesn't matter.
+                ctx.cursor.goto(e.row as f64, e.col as f64, 0);
+            }
+        }
+    }
+
+    fn default_colors_set(
+        &mut self,
+        DefaultColorsSet { fg, bg, sp }: DefaultColorsSet,
+    ) {
+        self.hl_defs.default_fg = fg;
+        self.hl_defs.default_bg = bg;
+        self.hl_defs.default_sp = sp;
+
+        if let Some(hl) = self.hl_defs.get_mut(&0) {
+            hl.foreground = Some(fg);
+            hl.background = Some(bg);
+            hl.special = Some(sp);
+        }
+
+        if let Some(ref mut ctx) = self.context {
+            render::redraw(ctx, &self.pango_context, &self.hl_defs);
+        }
+    }
+
+    fn option_set(&mut self, opt: OptionSet) {
+        match opt {
+            OptionSet::GuiFont(font) => {
+                self.font = Font::from_guifont(&font).unwrap_or_default();
+            }
+            OptionSet::GuiFontWide(font) => {
+                self.font_wide = Font::from_guifont(&font).ok();
+            }
+            OptionSet::LineSpace(val) => self.line_space = val,
+            _ => return,
+        }
+
+        // Apply the new metrics by "resizing" the grid to its current size.
+        if let Some(ref mut ctx) = self.context {
+            ctx.cell_metrics.font = self.font.clone();
+            ctx.cell_metrics.font_wide = self.font_wide.clone();
+            ctx.cell_metrics.line_space = self.line_space;
+            glyph_cache::clear();
+
+            let rows = ctx.rows.len();
+            let cols = ctx.rows.get(0).map(|r| r.len()).unwrap_or(0);
+            ctx.resize_headless(&self.pango_context, cols, rows, &self.hl_defs);
+            render::redraw(ctx, &self.pango_context, &self.hl_defs);
+        }
+    }
+
+    fn set_mode(&mut self) {
+        if let (Some(ctx), Some(mode)) =
+            (self.context.as_mut(), self.current_mode.as_ref())
+        {
+            ctx.cursor.blink_on = mode.blink_on;
+            ctx.cursor.cell_percentage = mode.cell_percentage;
+        }
+    }
+
+    /// Draws the grid (and the cursor) to a new image. Returns `None` if we
+    /// haven't received `grid_resize` for our grid yet.
+    pub fn render_image(&mut self) -> Option<cairo::ImageSurface> {
+        let ctx = self.context.as_mut()?;
+
+        // Draw the cursor cell like `Grid::flush` does (without blinking).
+        if let Some((row, col)) = ctx.row_at_cursor() {
+            render::cursor_cell(
+                &ctx.cursor_context,
+                &self.pango_context,
+                row,
+                col,
+                &ctx.cell_metrics,
+                &self.hl_defs,
+            );
+        }
+
+        let (w, h) = surface_size(ctx);
+        let surface = cairo::ImageSurface::create(cairo::Format::Rgb24, w, h)
+            .expect("Failed to create image surface");
+        {
+            let cr = cairo::Context::new(&surface);
+            drawingarea_draw(&cr, ctx);
+        }
+        surface.flush();
+
+        Some(surface)
+    }
+
+    /// Writes the grid as a png image to `w`.
+    pub fn write_png<W: io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        let surface = self.render_image().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "Nothing to render")
+        })?;
+
+        surface.write_to_png(w).map_err(|err| {
+            io::Error::new(io::ErrorKind::Other, err.to_string())
+        })
+    }
+}
+
+/// Size of the area that `ctx` covers.
+fn surface_size(ctx: &Context) -> (i32, i32) {
+    let cm = &ctx.cell_metrics;
+    let rows = ctx.rows.len() as f64;
+    let cols = ctx.rows.get(0).map(|r| r.len()).unwrap_or(0) as f64;
+
+    (
+        (cols * cm.width).ceil() as i32,
+        (rows * cm.height).ceil() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::nvim_bridge::{Cell, GridLineSegment, HlAttrDefine};
+    use crate::ui::color::Color;
+
+    /// Returns the color at (`x`, `y`) in `surface` as 0xRRGGBB.
+    fn pixel(surface: &mut cairo::ImageSurface, x: i32, y: i32) -> u32 {
+        let stride = surface.get_stride();
+        let data = surface.get_data().unwrap();
+        let i = (y * stride + x * 4) as usize;
+        let mut px = [0; 4];
+        px.copy_from_slice(&data[i..i + 4]);
+        u32::from_ne_bytes(px) & 0x00ff_ffff
+    }
+
+    #[test]
+    fn test_headless_grid_renders_highlights() {
+        let mut grid = HeadlessGrid::new(1, Font::default(), 0);
+
+        grid.handle_redraw_event(RedrawEvent::DefaultColorsSet(vec![
+            DefaultColorsSet {
+                fg: Color::from_u64(0xffffff),
+                bg: Color::from_u64(0x000000),
+                sp: Color::from_u64(0xff0000),
+            },
+        ]));
+        grid.handle_redraw_event(RedrawEvent::HlAttrDefine(vec![
+            HlAttrDefine {
+                id: 1,
+                hl: Highlight {
+                    background: Some(Color::from_u64(0x0000ff)),
+                    ..Highlight::default()
+                },
+                info: vec![],
+            },
+        ]));
+        grid.handle_redraw_event(RedrawEvent::GridResize(vec![GridResize {
+            grid: 1,
+            width: 10,
+            height: 2,
+        }]));
+        grid.handle_redraw_event(RedrawEvent::GridLine(vec![
+            GridLineSegment {
+                grid: 1,
+                row: 1,
+                col_start: 2,
+                cells: vec![Cell {
+                    text: String::from(" "),
+                    hl_id: 1,
+                    repeat: 3,
+                    double_width: false,
+                }],
+            },
+        ]));
+        // Put the cursor out of the way.
+        grid.handle_redraw_event(RedrawEvent::GridCursorGoto(vec![
+            GridCursorGoto {
+                grid: 1,
+                row: 0,
+                col: 9,
+            },
+        ]));
+
+        let mut image = grid.render_image().unwrap();
+        let (cw, ch) = {
+            let cm = &grid.context.as_ref().unwrap().cell_metrics;
+            (cm.width, cm.height)
+        };
+
+        // Middle of the highlighted cells.
+        let x = (3.5 * cw) as i32;
+        let y = (1.5 * ch) as i32;
+        assert_eq!(pixel(&mut image, x, y), 0x0000ff);
+
+        // Default background.
+        let x = (0.5 * cw) as i32;
+        assert_eq!(pixel(&mut image, x, y), 0x000000);
+
+        // The cursor, drawn with the inverted colors (e.g. fg as bg).
+        let x = (9.5 * cw) as i32;
+        let y = (0.5 * ch) as i32;
+        assert_eq!(pixel(&mut image, x, y), 0xffffff);
+    }
+
+    #[test]
+    fn test_headless_grid_renders_strikethrough() {
+        let mut grid = HeadlessGrid::new(1, Font::default(), 0);
+
+        grid.handle_redraw_event(RedrawEvent::DefaultColorsSet(vec![
+            DefaultColorsSet {
+                fg: Color::from_u64(0xffffff),
+                bg: Color::from_u64(0x000000),
+                sp: Color::from_u64(0xff0000),
+            },
+        ]));
+        grid.handle_redraw_event(RedrawEvent::HlAttrDefine(vec![
+            HlAttrDefine {
+                id: 1,
+                hl: Highlight {
+                    strikethrough: true,
+                    ..Highlight::default()
+                },
+                info: vec![],
+            },
+        ]));
+        grid.handle_redraw_event(RedrawEvent::GridResize(vec![GridResize {
+            grid: 1,
+            width: 10,
+            height: 1,
+        }]));
+        grid.handle_redraw_event(RedrawEvent::GridLine(vec![
+            GridLineSegment {
+                grid: 1,
+                row: 0,
+                col_start: 0,
+                cells: vec![Cell {
+                    text: String::from(" "),
+                    hl_id: 1,
+                    repeat: 3,
+                    double_width: false,
+                }],
+            },
+        ]));
+        grid.handle_redraw_event(RedrawEvent::GridCursorGoto(vec![
+            GridCursorGoto {
+                grid: 1,
+                row: 0,
+                col: 9,
+            },
+        ]));
+
+        let mut image = grid.render_image().unwrap();
+        let (cw, ch) = {
+            let cm = &grid.context.as_ref().unwrap().cell_metrics;
+            (cm.width, cm.height)
+        };
+
+        // The cells are blank, so anything that isn't the background is
+        // the strikethrough.
+        let x = (1.5 * cw) as i32;
+        let striked = (0..ch as i32)
+            .map(|y| pixel(&mut image, x, y))
+            .filter(|px| *px != 0x000000)
+            .collect::<Vec<_>>();
+        assert!(!striked.is_empty());
+        assert!(striked.iter().all(|px| px & 0x00ffff == 0));
+
+        // No strikethrough on the cells without the highlight.
+        let x = (5.5 * cw) as i32;
+        assert!((0..ch as i32).all(|y| pixel(&mut image, x, y) == 0x000000));
+    }
+}
@@ -1,8 +1,12 @@
 mod context;
 mod cursor;
+mod glyph_cache;
 #[allow(clippy::module_inception)]
 mod grid;
+mod headless;
 mod render;
 mod row;
+mod selection;
 
 pub use self::grid::{Grid, GridMetrics};
+pub use self::headless::HeadlessGrid;
@@ -1,94 +1,309 @@
-use gtk::prelude::*;
-use gtk::DrawingArea;
-use pango::Attribute;
+use pango::{Attribute, FontExt};
 
 use crate::nvim_bridge::GridLineSegment;
-use crate::ui::color::Highlight;
-use crate::ui::color::HlDefs;
+use crate::ui::color::{Color, Highlight, HlDefs};
+use crate::ui::font::Font;
 use crate::ui::grid::context::{CellMetrics, Context};
-use crate::ui::grid::row::{Cell, Segment};
+use crate::ui::grid::glyph_cache::{self, GlyphKey, ShapedItem};
+use crate::ui::grid::row::Row;
+
+/// Returns the x offset and scale that fit glyphs that are `width` wide
+/// into `cells_width`. Too wide glyphs are scaled down and too narrow ones
+/// are centered.
+fn fit_glyphs(width: f64, cells_width: f64) -> (f64, f64) {
+    if width > cells_width + 0.5 {
+        (0.0, cells_width / width)
+    } else if width < cells_width - 0.5 {
+        ((cells_width - width) / 2.0, 1.0)
+    } else {
+        (0.0, 1.0)
+    }
+}
 
-/// Renders text to `cr`.
+/// Itemizes and shapes `text` with the given font variant.
 ///
-/// * `cr` - The cairo context to render to.
-/// * `pango_context` - The pango context to use for text rendering.
-/// * `cm` - Cell metrics to use for text placement.
-/// * `hl` - The highlighting to use.
-/// * `hl_defs` - Global hl defs. Used to get default values.
-/// * `text` - The text to render.
-/// * `x` - Target x coordinate for `cr`.
-/// * `y` - Target y coordinate for `cr`.
-/// * `w` - Target width for `cr`.
-/// * `h` - Target height for `cr`.
-#[allow(clippy::too_many_arguments, clippy::many_single_char_names)]
-fn render_text(
-    cr: &cairo::Context,
+/// * `cells` - How many cells each char of `text` takes.
+/// * `font` - Font to use instead of the context's font (e.g. the wide
+///            font).
+fn shape_text(
     pango_context: &pango::Context,
     cm: &CellMetrics,
-    hl: &Highlight,
-    hl_defs: &HlDefs,
     text: &str,
-    x: f64,
-    y: f64,
-    w: f64,
-    h: f64,
-) {
-    let (fg, bg) = if hl.reverse {
-        (
-            hl.background.unwrap_or(hl_defs.default_bg),
-            hl.foreground.unwrap_or(hl_defs.default_fg),
-        )
-    } else {
-        (
-            hl.foreground.unwrap_or(hl_defs.default_fg),
-            hl.background.unwrap_or(hl_defs.default_bg),
-        )
-    };
-
-    cr.save();
-    cr.set_source_rgb(bg.r, bg.g, bg.b);
-    cr.rectangle(x, y, w, h);
-    cr.fill();
-    cr.restore();
-
+    cells: &[usize],
+    bold: bool,
+    italic: bool,
+    font: Option<&Font>,
+) -> Vec<ShapedItem> {
     let attrs = pango::AttrList::new();
 
-    if hl.bold {
+    if let Some(font) = font {
+        for attr in font.as_pango_attrs() {
+            attrs.insert(attr);
+        }
+    }
+
+    if bold {
         let attr = Attribute::new_weight(pango::Weight::Bold).unwrap();
         attrs.insert(attr);
     }
-    if hl.italic {
+    if italic {
         let attr = Attribute::new_style(pango::Style::Italic).unwrap();
         attrs.insert(attr);
     }
 
-    cr.save();
-    cr.set_source_rgb(fg.r, fg.g, fg.b);
-
     let items =
         pango::itemize(pango_context, text, 0, text.len() as i32, &attrs, None);
 
-    let mut x_offset = 0.0;
-    for item in items {
-        let a = item.analysis();
-        let item_offset = item.offset() as usize;
-        let mut glyphs = pango::GlyphString::new();
+    // Glyphs from the context's (first) font fill their cells. Glyphs from
+    // other fonts (fallbacks or the wide font) might not.
+    let family =
+        |font: &pango::Font| font.describe().and_then(|d| d.get_family());
+    let primary = pango_context
+        .get_font_description()
+        .and_then(|desc| pango_context.load_font(&desc))
+        .and_then(|font| family(&font));
+
+    items
+        .into_iter()
+        .map(|item| {
+            let a = item.analysis();
+            let item_offset = item.offset() as usize;
+            let item_text =
+                &text[item_offset..item_offset + item.length() as usize];
+            let mut glyphs = pango::GlyphString::new();
+
+            pango::shape(item_text, &a, &mut glyphs);
+
+            let first = text[..item_offset].chars().count();
+            let cells: usize = cells
+                .iter()
+                .skip(first)
+                .take(item_text.chars().count())
+                .sum();
+
+            let font = a.font();
+            let (x_offset, scale) = if family(&font) != primary {
+                let width =
+                    f64::from(glyphs.get_width()) / f64::from(pango::SCALE);
+                fit_glyphs(width, cells as f64 * cm.width)
+            } else {
+                (0.0, 1.0)
+            };
+
+            ShapedItem {
+                font,
+                glyphs,
+                cells,
+                x_offset,
+                scale,
+            }
+        })
+        .collect()
+}
 
-        pango::shape(
-            &text[item_offset..item_offset + item.length() as usize],
-            &a,
-            &mut glyphs,
-        );
+/// Cells that are shaped together. Ligatures can span multiple highlights,
+/// so a run spans all the consecutive cells that use the same font and
+/// font variant. Ligatures don't span whitespace, so runs are split there to
+/// keep them short.
+#[derive(Debug, PartialEq)]
+struct Run {
+    text: String,
+    /// How many cells each char of `text` takes. Nvim tells the width of
+    /// the chars by following double width chars with an empty cell.
+    /// Combining chars take no cells.
+    cells: Vec<usize>,
+    start: usize,
+    len: usize,
+    bold: bool,
+    italic: bool,
+    /// If the run is double width chars, which use the wide font.
+    wide: bool,
+    /// If the run is just whitespace, and doesn't need to be shaped.
+    blank: bool,
+}
 
-        cr.move_to(x + x_offset, y + cm.ascent);
-        pangocairo::functions::show_glyph_string(&cr, &a.font(), &mut glyphs);
+impl Run {
+    fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+/// Splits `row` into runs.
+fn runs(row: &Row, hl_defs: &HlDefs) -> Vec<Run> {
+    let mut runs: Vec<Run> = vec![];
+
+    for i in 0..row.len {
+        let cell = row.cell_at(i).unwrap();
+        let (bold, italic) = hl_defs
+            .get(&cell.hl_id)
+            .map(|hl| (hl.bold, hl.italic))
+            .unwrap_or_default();
+        let blank = cell.text == " ";
+        let wide = cell.double_width;
+
+        if let Some(run) = runs.last_mut() {
+            // Empty cells (after double width chars) belong to the run of
+            // the char, and the font doesn't matter for whitespace.
+            if cell.text.is_empty() {
+                if let Some(cells) =
+                    run.cells.iter_mut().rev().find(|c| **c > 0)
+                {
+                    *cells += 1;
+                }
+                run.len += 1;
+                continue;
+            }
+
+            if (run.blank && blank)
+                || (!run.blank
+                    && !blank
+                    && run.bold == bold
+                    && run.italic == italic
+                    && run.wide == wide)
+            {
+                run.text.push_str(&cell.text);
+                run.cells.extend(char_cells(&cell.text));
+                run.len += 1;
+                continue;
+            }
+        }
+
+        runs.push(Run {
+            text: cell.text.clone(),
+            cells: char_cells(&cell.text).collect(),
+            start: i,
+            len: 1,
+            bold,
+            italic,
+            wide,
+            blank,
+        });
+    }
+
+    runs
+}
+
+/// Cells taken by each char of a cell's `text`. The first char takes the
+/// cell, and the rest are combining chars.
+fn char_cells(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.chars()
+        .enumerate()
+        .map(|(i, _)| if i == 0 { 1 } else { 0 })
+}
+
+/// Splits the cells from `start` to `end` (exclusive) of `row` into
+/// highlight segments, as (start, len, hl_id).
+fn hl_segments(
+    row: &Row,
+    start: usize,
+    end: usize,
+) -> Vec<(usize, usize, u64)> {
+    let mut segs: Vec<(usize, usize, u64)> = vec![];
+
+    for i in start..end {
+        let hl_id = row.cell_at(i).unwrap().hl_id;
+        match segs.last_mut() {
+            Some((_, len, id)) if *id == hl_id => *len += 1,
+            _ => segs.push((i, 1, hl_id)),
+        }
+    }
 
-        x_offset += f64::from(item.num_chars()) * cm.width;
-        //x_offset += f64::from(glyphs.get_width());
+    segs
+}
+
+/// Returns the foreground and background colors of `hl`.
+fn hl_colors(hl: &Highlight, hl_defs: &HlDefs) -> (Color, Color) {
+    if hl.reverse {
+        (
+            hl.background.unwrap_or(hl_defs.default_bg),
+            hl.foreground.unwrap_or(hl_defs.default_fg),
+        )
+    } else {
+        (
+            hl.foreground.unwrap_or(hl_defs.default_fg),
+            hl.background.unwrap_or(hl_defs.default_bg),
+        )
     }
+}
+
+/// Draws the glyphs of `run` to `cr`. Uses the current source of `cr`.
+///
+/// * `x` - Target x coordinate of the run's first cell.
+/// * `y` - Target y coordinate of the run's first cell.
+fn show_run(
+    cr: &cairo::Context,
+    pango_context: &pango::Context,
+    cm: &CellMetrics,
+    run: &Run,
+    x: f64,
+    y: f64,
+) {
+    let font = if run.wide {
+        cm.font_wide.as_ref()
+    } else {
+        None
+    };
+
+    glyph_cache::with_shaped(
+        GlyphKey::new(
+            &run.text,
+            &run.cells,
+            run.bold,
+            run.italic,
+            font.is_some(),
+        ),
+        || {
+            shape_text(
+                pango_context,
+                cm,
+                &run.text,
+                &run.cells,
+                run.bold,
+                run.italic,
+                font,
+            )
+        },
+        |items| {
+            let mut x_offset = 0.0;
+            for item in items {
+                cr.save();
+                cr.translate(x + x_offset + item.x_offset, y + cm.ascent);
+                cr.scale(item.scale, item.scale);
+                cr.move_to(0.0, 0.0);
+                pangocairo::functions::show_glyph_string(
+                    &cr,
+                    &item.font,
+                    &mut item.glyphs,
+                );
+                cr.restore();
+
+                x_offset += item.cells as f64 * cm.width;
+            }
+        },
+    );
+}
+
+/// Draws the underlines and strikethrough of `hl` to `cr`.
+///
+/// * `x` - Target x coordinate for `cr`.
+/// * `y` - Target y coordinate for `cr`.
+/// * `w` - Target width for `cr`.
+/// * `h` - Target height for `cr`.
+#[allow(clippy::too_many_arguments, clippy::many_single_char_names)]
+fn render_decorations(
+    cr: &cairo::Context,
+    cm: &CellMetrics,
+    hl: &Highlight,
+    hl_defs: &HlDefs,
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+) {
+    cr.save();
 
     // Since we can't (for some reason) use pango attributes to draw
-    // underline and undercurl, we'll have to do that manually.
+    // underlines and strikethrough, we'll have to do that manually.
     let sp = hl.special.unwrap_or(hl_defs.default_sp);
     cr.set_source_rgb(sp.r, sp.g, sp.b);
     if hl.undercurl {
@@ -105,24 +320,60 @@ fn render_text(
         cr.rectangle(x, y, w, cm.underline_thickness);
         cr.fill();
     }
+    if hl.underdouble {
+        // Two thinner lines, so we'll stay in the cell.
+        let t = (cm.underline_thickness / 2.0).max(1.0);
+        let y = y + h + cm.underline_position;
+        cr.rectangle(x, y - t * 2.0, w, t);
+        cr.rectangle(x, y, w, t);
+        cr.fill();
+    }
+    if hl.underdotted || hl.underdashed {
+        let t = cm.underline_thickness;
+        let dashes = if hl.underdotted {
+            [t, t]
+        } else {
+            [t * 3.0, t * 2.0]
+        };
+        let y = y + h + cm.underline_position + t / 2.0;
+
+        cr.save();
+        cr.set_line_width(t);
+        // Offset by x, so the pattern continues over segments.
+        cr.set_dash(&dashes, x);
+        cr.move_to(x, y);
+        cr.line_to(x + w, y);
+        cr.stroke();
+        cr.restore();
+    }
+    if hl.strikethrough {
+        let y = y + cm.ascent - cm.strikethrough_position;
+        cr.rectangle(x, y, w, cm.strikethrough_thickness.max(1.0));
+        cr.fill();
+    }
 
     cr.restore();
 }
 
-/// Draws (inverted) cell to `cr`.
+/// Draws (inverted) cell at `col` on `row` to `cr`. If the cell is a part
+/// of a ligature, only that part of the ligature is drawn.
 pub fn cursor_cell(
     cr: &cairo::Context,
     pango_context: &pango::Context,
-    cell: &Cell,
+    row: &Row,
+    col: usize,
     cm: &CellMetrics,
     hl_defs: &HlDefs,
 ) {
-    let mut hl = *hl_defs.get(&cell.hl_id).unwrap();
+    let cell = match row.cell_at(col) {
+        Some(cell) => cell,
+        None => return,
+    };
 
+    let mut hl = *hl_defs.get(&cell.hl_id).unwrap();
     hl.reverse = !hl.reverse;
+    let (fg, bg) = hl_colors(&hl, hl_defs);
 
-    let x = 0.0;
-    let y = 0.0;
     let w = if cell.double_width {
         cm.width * 2.0
     } else {
@@ -130,35 +381,119 @@ pub fn cursor_cell(
     };
     let h = cm.height;
 
-    render_text(cr, pango_context, cm, &hl, hl_defs, &cell.text, x, y, w, h);
+    cr.save();
+    cr.set_source_rgb(bg.r, bg.g, bg.b);
+    cr.rectangle(0.0, 0.0, w, h);
+    cr.fill();
+
+    if let Some(run) = runs(row, hl_defs)
+        .into_iter()
+        .find(|run| run.start <= col && col < run.end())
+        .filter(|run| !run.blank)
+    {
+        // Draw the whole run so that the cell looks the same as on the
+        // grid, but only the cell's part of it.
+        cr.save();
+        cr.rectangle(0.0, -h, w, h * 3.0);
+        cr.clip();
+        cr.set_source_rgb(fg.r, fg.g, fg.b);
+        let x = -((col - run.start) as f64 * cm.width);
+        show_run(cr, pango_context, cm, &run, x, 0.0);
+        cr.restore();
+    }
+
+    render_decorations(cr, cm, &hl, hl_defs, 0.0, 0.0, w, h);
+    cr.restore();
 }
 
-/// Renders `segments` to `cr`.
-fn put_segments(
+/// Renders the cells from `start` to `end` (exclusive) of `row` to `cr`.
+/// The range is extended to whole runs, so that ligatures are reshaped even
+/// if only a part of them changed.
+#[allow(clippy::too_many_arguments)]
+fn put_cells(
     cr: &cairo::Context,
     pango_context: &pango::Context,
     queue_draw_area: &mut Vec<(f64, f64, f64, f64)>,
     cm: &CellMetrics,
     hl_defs: &HlDefs,
-    segments: Vec<Segment>,
-    row: usize,
+    row: &Row,
+    row_idx: usize,
+    start: usize,
+    end: usize,
 ) {
-    let cw = cm.width;
-    let ch = cm.height;
-
-    for seg in segments {
-        let hl = hl_defs.get(&seg.hl_id).unwrap();
+    let runs = runs(row, hl_defs)
+        .into_iter()
+        .filter(|run| run.start < end && start < run.end())
+        .collect::<Vec<_>>();
+    let (start, end) = match (runs.first(), runs.last()) {
+        (Some(first), Some(last)) => (first.start, last.end()),
+        _ => return,
+    };
 
-        let x = (seg.start as f64 * cw).floor();
-        let y = (row as f64 * ch).floor();
-        let w = (seg.len as f64 * cw).ceil();
-        let h = ch.ceil();
+    let cw = cm.width;
+    let y = (row_idx as f64 * cm.height).floor();
+    let h = cm.height.ceil();
+    let segs = hl_segments(row, start, end)
+        .into_iter()
+        .map(|(start, len, hl_id)| {
+            let x = (start as f64 * cw).floor();
+            let w = (len as f64 * cw).ceil();
+            (start, len, x, w, hl_defs.get(&hl_id).unwrap())
+        })
+        .collect::<Vec<_>>();
+
+    // Backgrounds first, so glyphs overflowing to the next cells won't get
+    // painted over.
+    for (_, _, x, w, hl) in segs.iter() {
+        let (_, bg) = hl_colors(hl, hl_defs);
+        cr.save();
+        cr.set_source_rgb(bg.r, bg.g, bg.b);
+        cr.rectangle(*x, y, *w, h);
+        cr.fill();
+        cr.restore();
+    }
 
-        let text = &seg.text;
-        render_text(cr, pango_context, cm, &hl, hl_defs, &text, x, y, w, h);
+    // Shape each run as a whole, and paint it with each highlight's color
+    // clipped to the highlight's cells.
+    for run in runs.iter().filter(|run| !run.blank) {
+        let run_x = (run.start as f64 * cw).floor();
+        let run_segs = segs
+            .iter()
+            .filter(|(start, len, ..)| {
+                *start < run.end() && run.start < start + len
+            })
+            .collect::<Vec<_>>();
+
+        for (i, (_, _, x, w, hl)) in run_segs.iter().enumerate() {
+            let (fg, _) = hl_colors(hl, hl_defs);
+            cr.save();
+
+            if run_segs.len() > 1 {
+                // Don't clip the run's ends, so that overflowing glyphs
+                // (e.g. italics) still show up.
+                let left = if i == 0 { 0.0 } else { *x };
+                let right = if i == run_segs.len() - 1 {
+                    f64::from(i32::MAX)
+                } else {
+                    x + w
+                };
+                cr.rectangle(left, y - h, right - left, h * 3.0);
+                cr.clip();
+            }
+
+            cr.set_source_rgb(fg.r, fg.g, fg.b);
+            show_run(cr, pango_context, cm, run, run_x, y);
+            cr.restore();
+        }
+    }
 
-        queue_draw_area.push((x, y, w, h));
+    for (_, _, x, w, hl) in segs.iter() {
+        render_decorations(cr, cm, hl, hl_defs, *x, y, *w, h);
     }
+
+    let x = (start as f64 * cw).floor();
+    let w = ((end - start) as f64 * cw).ceil();
+    queue_draw_area.push((x, y, w, h));
 }
 
 pub fn redraw(
@@ -166,17 +501,17 @@ pub fn redraw(
     pango_context: &pango::Context,
     hl_defs: &HlDefs,
 ) {
-    for (i, row) in context.rows.iter_mut().enumerate() {
-        let segments = row.as_segments(0, row.len);
-
-        put_segments(
+    for (i, row) in context.rows.iter().enumerate() {
+        put_cells(
             &context.cairo_context,
             pango_context,
             &mut context.queue_draw_area,
             &context.cell_metrics,
             hl_defs,
-            segments,
+            row,
             i,
+            0,
+            row.len,
         );
     }
 }
@@ -188,35 +523,37 @@ pub fn put_line(
     line: GridLineSegment,
     hl_defs: &HlDefs,
 ) {
-    let row = line.row as usize;
-    let mut affected_segments = context
+    let row_idx = line.row as usize;
+    let row = context
         .rows
-        .get_mut(row)
-        .unwrap_or_else(|| panic!("Failed to get row {}", line.row))
-        .update(line);
-
-    // NOTE(ville): I haven't noticed any cases where a character is overflowing
-    //              to the left. Probably doesn't apply to languages that goes
-    //              from right to left, instead of left to right.
-    // Rendering the segments in reversed order fixes issues when some character
-    // is overflowing to the right.
-    affected_segments.reverse();
-    put_segments(
-        &context.cairo_context,
-        pango_context,
-        &mut context.queue_draw_area,
-        &context.cell_metrics,
-        hl_defs,
-        affected_segments,
-        row,
-    );
+        .get_mut(row_idx)
+        .unwrap_or_else(|| panic!("Failed to get row {}", line.row));
+    let affected_segments = row.update(line);
+
+    let start = affected_segments.iter().map(|seg| seg.start).min();
+    let end = affected_segments
+        .iter()
+        .map(|seg| seg.start + seg.len)
+        .max();
+    if let (Some(start), Some(end)) = (start, end) {
+        put_cells(
+            &context.cairo_context,
+            pango_context,
+            &mut context.queue_draw_area,
+            &context.cell_metrics,
+            hl_defs,
+            &context.rows[row_idx],
+            row_idx,
+            start,
+            end,
+        );
+    }
 }
 
-/// Clears whole `da` with `hl_defs.default_bg`.
-pub fn clear(da: &DrawingArea, ctx: &mut Context, hl_defs: &HlDefs) {
+/// Clears area of `w` and `h` (e.g. the whole drawing area) with
+/// `hl_defs.default_bg`.
+pub fn clear(ctx: &mut Context, hl_defs: &HlDefs, w: i32, h: i32) {
     let cr = &ctx.cairo_context;
-    let w = da.get_allocated_width();
-    let h = da.get_allocated_height();
     let bg = &hl_defs.default_bg;
 
     cr.save();
@@ -342,3 +679,148 @@ pub fn get_coords(h: f64, w: f64, row: f64, col: f64) -> (f64, f64) {
     let y = row * h;
     (x, y)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ui::grid::row::Cell;
+
+    /// Row with `text`, and each char's hl id from `hl_ids`.
+    fn row(text: &str, hl_ids: &[u64]) -> Row {
+        let mut row = Row::new(text.chars().count());
+        row.insert_at(
+            0,
+            text.chars()
+                .zip(hl_ids)
+                .map(|(c, hl_id)| Cell {
+                    text: c.to_string(),
+                    hl_id: *hl_id,
+                    double_width: false,
+                })
+                .collect(),
+        );
+        row
+    }
+
+    fn hl_defs() -> HlDefs {
+        let mut hl_defs = HlDefs::default();
+        hl_defs.insert(0, Highlight::default());
+        // Search match.
+        hl_defs.insert(1, Highlight::default());
+        hl_defs.insert(
+            2,
+            Highlight {
+                bold: true,
+                ..Highlight::default()
+            },
+        );
+        hl_defs
+    }
+
+    #[test]
+    fn test_runs_span_highlights() {
+        let row = row("a->b c", &[0, 0, 1, 0, 0, 0]);
+
+        assert_eq!(
+            runs(&row, &hl_defs())
+                .into_iter()
+                .map(|run| (run.text, run.start, run.len, run.blank))
+                .collect::<Vec<_>>(),
+            vec![
+                ("a->b".to_string(), 0, 4, false),
+                (" ".to_string(), 4, 1, true),
+                ("c".to_string(), 5, 1, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runs_split_on_font_variant() {
+        let row = row("a->b", &[0, 2, 2, 0]);
+
+        assert_eq!(
+            runs(&row, &hl_defs())
+                .into_iter()
+                .map(|run| (run.text, run.bold))
+                .collect::<Vec<_>>(),
+            vec![
+                ("a".to_string(), false),
+                ("->".to_string(), true),
+                ("b".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runs_split_on_double_width() {
+        let mut row = row("abc", &[0, 0, 0]);
+        row.insert_at(
+            1,
+            vec![
+                Cell {
+                    text: "日".to_string(),
+                    hl_id: 0,
+                    double_width: true,
+                },
+                Cell {
+                    text: "".to_string(),
+                    hl_id: 0,
+                    double_width: false,
+                },
+            ],
+        );
+
+        assert_eq!(
+            runs(&row, &hl_defs())
+                .into_iter()
+                .map(|run| (run.text, run.cells, run.len, run.wide))
+                .collect::<Vec<_>>(),
+            vec![
+                ("a".to_string(), vec![1], 1, false),
+                ("日".to_string(), vec![2], 2, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runs_combining_chars() {
+        let mut row = row("ab", &[0, 0]);
+        row.insert_at(
+            0,
+            vec![Cell {
+                text: "e\u{301}".to_string(),
+                hl_id: 0,
+                double_width: false,
+            }],
+        );
+
+        assert_eq!(
+            runs(&row, &hl_defs())
+                .into_iter()
+                .map(|run| (run.text, run.cells, run.len))
+                .collect::<Vec<_>>(),
+            vec![("e\u{301}b".to_string(), vec![1, 0, 1], 2)]
+        );
+    }
+
+    #[test]
+    fn test_fit_glyphs() {
+        // Fits already.
+        assert_eq!(fit_glyphs(10.2, 10.0), (0.0, 1.0));
+        // Too narrow, centered.
+        assert_eq!(fit_glyphs(6.0, 10.0), (2.0, 1.0));
+        // Too wide, scaled.
+        assert_eq!(fit_glyphs(20.0, 10.0), (0.0, 0.5));
+    }
+
+    #[test]
+    fn test_hl_segments() {
+        let row = row("a->b c", &[0, 0, 1, 0, 0, 0]);
+
+        assert_eq!(
+            hl_segments(&row, 1, 4),
+            vec![(1, 1, 0), (2, 1, 1), (3, 1, 0)]
+        );
+    }
+}
@@ -198,6 +198,11 @@ mod benches {
 
     use super::*;
 
+    use crate::nvim_bridge::{GridResize, RedrawEvent};
+    use crate::ui::font::Font;
+    use crate::ui::grid::glyph_cache;
+    use crate::ui::grid::HeadlessGrid;
+
     #[bench]
     fn bench_row_update(b: &mut Bencher) {
         let mut row = Row::new(10);
@@ -460,6 +465,77 @@ mod benches {
             );
         });
     }
+
+    const COLS: u64 = 80;
+    const ROWS: u64 = 50;
+
+    /// Full width `grid_line` for `text`.
+    fn grid_line(row: u64, text: &str) -> GridLineSegment {
+        GridLineSegment {
+            grid: 1,
+            row,
+            col_start: 0,
+            cells: format!("{:width$}", text, width = COLS as usize)
+                .chars()
+                .map(|c| nvim_bridge::Cell {
+                    text: c.to_string(),
+                    hl_id: 0,
+                    repeat: 1,
+                    double_width: false,
+                })
+                .collect(),
+        }
+    }
+
+    /// Scrolls through a file one line at a time, redrawing the whole grid
+    /// each time (like nvim does when scrolling with `<C-e>` without
+    /// `grid_scroll`).
+    fn scroll_file(b: &mut Bencher, cached: bool) {
+        let mut grid = HeadlessGrid::new(1, Font::default(), 0);
+        grid.handle_redraw_event(RedrawEvent::GridResize(vec![GridResize {
+            grid: 1,
+            width: COLS,
+            height: ROWS,
+        }]));
+
+        let lines = (0..500)
+            .map(|i| {
+                format!(
+                    "    let value_{} = compute(&value_{}, \"item {}\");",
+                    i % 30,
+                    i % 7,
+                    i
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut top = 0;
+        b.iter(|| {
+            for row in 0..ROWS {
+                if !cached {
+                    glyph_cache::clear();
+                }
+
+                let text = &lines[(top + row as usize) % lines.len()];
+                grid.handle_redraw_event(RedrawEvent::GridLine(vec![
+                    grid_line(row, text),
+                ]));
+            }
+
+            grid.handle_redraw_event(RedrawEvent::Flush());
+            top += 1;
+        });
+    }
+
+    #[bench]
+    fn bench_scroll_file(b: &mut Bencher) {
+        scroll_file(b, true);
+    }
+
+    #[bench]
+    fn bench_scroll_file_uncached(b: &mut Bencher) {
+        scroll_file(b, false);
+    }
 }
 
 #[cfg(test)]
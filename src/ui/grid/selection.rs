@@ -0,0 +1,205 @@
+use crate::ui::grid::row::Row;
+
+/// GUI-local text selection on a grid, used when nvim's mouse support is
+/// disabled. Spans the cells from `start` to `end` (inclusive) in reading
+/// order, like in terminals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    /// Row and col where the selection started.
+    pub start: (u64, u64),
+    /// Row and col where the selection currently ends.
+    pub end: (u64, u64),
+}
+
+impl Selection {
+    pub fn new(start: (u64, u64), end: (u64, u64)) -> Self {
+        Selection { start, end }
+    }
+
+    /// Start and end in reading order.
+    fn ordered(&self) -> ((u64, u64), (u64, u64)) {
+        if self.start <= self.end {
+            (self.start, self.end)
+        } else {
+            (self.end, self.start)
+        }
+    }
+
+    /// Selected cells on each row, as (row, start col, end col). The end
+    /// is exclusive.
+    ///
+    /// * `cols` - Width of the grid.
+    pub fn row_ranges(&self, cols: u64) -> Vec<(u64, u64, u64)> {
+        let ((start_row, start_col), (end_row, end_col)) = self.ordered();
+
+        (start_row..=end_row)
+            .map(|row| {
+                let start = if row == start_row { start_col } else { 0 };
+                let end = if row == end_row { end_col + 1 } else { cols };
+                (row, start.min(cols), end.min(cols))
+            })
+            .collect()
+    }
+
+    /// Returns the selected text from `rows`. Trailing whitespace is removed
+    /// from each line.
+    pub fn text(&self, rows: &[Row]) -> String {
+        let cols = rows.get(0).map(|row| row.len).unwrap_or(0) as u64;
+
+        self.row_ranges(cols)
+            .into_iter()
+            .filter_map(|(row, start, end)| {
+                Some(range_text(rows.get(row as usize)?, start, end))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Returns the selected text on `row`, or `None` if the row isn't
+    /// selected. Used to see if nvim changed the selected text.
+    pub fn row_text(&self, rows: &[Row], row: u64) -> Option<String> {
+        let cols = rows.get(0).map(|row| row.len).unwrap_or(0) as u64;
+
+        self.row_ranges(cols)
+            .into_iter()
+            .find(|(r, _, _)| *r == row)
+            .and_then(|(row, start, end)| {
+                Some(range_text(rows.get(row as usize)?, start, end))
+            })
+    }
+
+    /// Returns the selection after `grid_scroll` of `reg` by `count` rows,
+    /// so it stays on the same text. `None` if the selected text moved
+    /// (partially) out of the region, or if the region only scrolls some of
+    /// the selected cells.
+    ///
+    /// * `reg` - Top, bot, left and right of the scroll region. Bot and right
+    ///           are exclusive.
+    /// * `cols` - Width of the grid.
+    pub fn scroll(&self, reg: [u64; 4], count: i64, cols: u64) -> Option<Self> {
+        let [top, bot, left, right] = reg;
+        let ((start_row, _), (end_row, _)) = self.ordered();
+
+        if end_row < top || start_row >= bot {
+            return Some(*self);
+        }
+
+        if start_row < top || end_row >= bot || left > 0 || right < cols {
+            return None;
+        }
+
+        let shift = |(row, col): (u64, u64)| {
+            let row = row as i64 - count;
+            if row < top as i64 || row >= bot as i64 {
+                None
+            } else {
+                Some((row as u64, col))
+            }
+        };
+
+        Some(Selection {
+            start: shift(self.start)?,
+            end: shift(self.end)?,
+        })
+    }
+}
+
+/// Text of the cells from `start` to `end` (exclusive) on `row`, without
+/// trailing whitespace.
+fn range_text(row: &Row, start: u64, end: u64) -> String {
+    (start..end)
+        .filter_map(|col| row.cell_at(col as usize))
+        .map(|cell| cell.text.as_str())
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ui::grid::row::Cell;
+
+    fn row(text: &str, len: usize) -> Row {
+        let mut row = Row::new(len);
+        row.insert_at(
+            0,
+            text.chars()
+                .map(|c| Cell {
+                    text: c.to_string(),
+                    hl_id: 0,
+                    double_width: false,
+                })
+                .collect(),
+        );
+        row
+    }
+
+    #[test]
+    fn test_selection_row_ranges() {
+        let sel = Selection {
+            start: (2, 3),
+            end: (0, 5),
+        };
+
+        assert_eq!(sel.row_ranges(10), vec![(0, 5, 10), (1, 0, 10), (2, 0, 4)]);
+    }
+
+    #[test]
+    fn test_selection_text() {
+        let rows =
+            vec![row("foo bar", 10), row("baz", 10), row("qux quux", 10)];
+        let sel = Selection {
+            start: (0, 4),
+            end: (2, 2),
+        };
+
+        assert_eq!(sel.text(&rows), "bar\nbaz\nqux");
+    }
+
+    #[test]
+    fn test_selection_row_text() {
+        let rows =
+            vec![row("foo bar", 10), row("baz", 10), row("qux quux", 10)];
+        let sel = Selection {
+            start: (0, 4),
+            end: (1, 1),
+        };
+
+        assert_eq!(sel.row_text(&rows, 0), Some(String::from("bar")));
+        assert_eq!(sel.row_text(&rows, 1), Some(String::from("ba")));
+        assert_eq!(sel.row_text(&rows, 2), None);
+    }
+
+    #[test]
+    fn test_selection_scroll() {
+        let sel = Selection {
+            start: (4, 3),
+            end: (5, 2),
+        };
+
+        // Scrolled up and down within the region.
+        assert_eq!(
+            sel.scroll([0, 10, 0, 20], 2, 20),
+            Some(Selection {
+                start: (2, 3),
+                end: (3, 2),
+            })
+        );
+        assert_eq!(
+            sel.scroll([0, 10, 0, 20], -3, 20),
+            Some(Selection {
+                start: (7, 3),
+                end: (8, 2),
+            })
+        );
+        // Region doesn't include the selection.
+        assert_eq!(sel.scroll([6, 10, 0, 20], 2, 20), Some(sel));
+        // Scrolled out of the region.
+        assert_eq!(sel.scroll([0, 10, 0, 20], 5, 20), None);
+        // Region covers the selection only partially.
+        assert_eq!(sel.scroll([5, 10, 0, 20], 1, 20), None);
+        assert_eq!(sel.scroll([0, 10, 0, 10], 1, 20), None);
+    }
+}
@@ -0,0 +1,401 @@
+use gtk::prelude::*;
+
+use crate::nvim_bridge::{MsgHistoryShow, MsgShow};
+use crate::ui::color::{Color, HlDefs, HlGroup};
+use crate::ui::common::calc_line_space;
+use crate::ui::font::{Font, FontUnit};
+
+const MAX_WIDTH: i32 = 650;
+/// Max height of the message and history views. After this, the views
+/// start to scroll.
+const MAX_HEIGHT: i32 = 300;
+
+/// Message chunks, each with its hl id.
+type Content = Vec<(u64, String)>;
+
+/// Returns `content` as pango markup.
+fn content_markup(content: &[(u64, String)], hl_defs: &HlDefs) -> String {
+    content
+        .iter()
+        .map(|(hl_id, text)| {
+            let hl = hl_defs.get(hl_id).cloned().unwrap_or_default();
+            hl.pango_markup(
+                text,
+                &hl_defs.default_fg,
+                &hl_defs.default_bg,
+                &hl_defs.default_sp,
+            )
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct MessagesColors {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    border: Option<Color>,
+}
+
+/// Scrollable, read only list of messages.
+struct MessageView {
+    frame: gtk::Frame,
+    textview: gtk::TextView,
+}
+
+impl MessageView {
+    fn new() -> Self {
+        let textview = gtk::TextView::new();
+        textview.set_editable(false);
+        textview.set_cursor_visible(false);
+        textview.set_wrap_mode(gtk::WrapMode::WordChar);
+
+        let scrolledwindow = gtk::ScrolledWindow::new(
+            None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>,
+        );
+        scrolledwindow
+            .set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolledwindow.add(&textview);
+
+        let frame = gtk::Frame::new(None);
+        frame.add(&scrolledwindow);
+
+        // Grow with the content, up until MAX_HEIGHT.
+        let scrolledwindow_weak = scrolledwindow.downgrade();
+        textview.connect_size_allocate(
+            clone!(scrolledwindow_weak => move |tv, _| {
+                let scrolledwindow = upgrade_weak!(scrolledwindow_weak);
+                let h = tv.get_preferred_height().1.min(MAX_HEIGHT);
+                if scrolledwindow.get_size_request().1 != h {
+                    scrolledwindow.set_size_request(-1, h);
+                }
+            }),
+        );
+
+        MessageView { frame, textview }
+    }
+
+    fn widget(&self) -> gtk::Widget {
+        self.frame.clone().upcast()
+    }
+
+    fn add_css_provider(&self, provider: &gtk::CssProvider) {
+        add_css_provider!(provider, self.frame, self.textview);
+    }
+
+    /// Replaces the content of the view with `messages` (one per line), and
+    /// scrolls to the last one.
+    fn set_messages<'a, I>(&self, messages: I, hl_defs: &HlDefs)
+    where
+        I: IntoIterator<Item = &'a Content>,
+    {
+        let buffer = self.textview.get_buffer().unwrap();
+        buffer.set_text("");
+
+        let mut iter = buffer.get_start_iter();
+        for (i, content) in messages.into_iter().enumerate() {
+            if i > 0 {
+                buffer.insert(&mut iter, "\n");
+            }
+            buffer.insert_markup(&mut iter, &content_markup(content, hl_defs));
+        }
+
+        let mark = buffer.create_mark(None, &iter, false).unwrap();
+        self.textview.scroll_to_mark(&mark, 0.0, false, 0.0, 0.0);
+    }
+
+    fn set_line_space(&self, space: i64) {
+        let (above, below) = calc_line_space(space);
+        self.textview.set_pixels_above_lines(above);
+        self.textview.set_pixels_below_lines(below);
+    }
+}
+
+/// Shows nvim's messages (see `:h ui-messages`).
+pub struct Messages {
+    css_provider: gtk::CssProvider,
+    /// Container for everything.
+    box_: gtk::Box,
+
+    /// Messages from `msg_show`.
+    view: MessageView,
+    /// History from `msg_history_show` (e.g. `:messages`).
+    history_view: MessageView,
+
+    status: gtk::Box,
+    showmode_label: gtk::Label,
+    showcmd_label: gtk::Label,
+    ruler_label: gtk::Label,
+
+    messages: Vec<Content>,
+    history: Vec<Content>,
+    showmode: Content,
+    showcmd: Content,
+    ruler: Content,
+
+    colors: MessagesColors,
+    font: Font,
+}
+
+impl Messages {
+    pub fn new(parent: &gtk::Overlay) -> Self {
+        let css_provider = gtk::CssProvider::new();
+
+        let view = MessageView::new();
+        let history_view = MessageView::new();
+        view.add_css_provider(&css_provider);
+        history_view.add_css_provider(&css_provider);
+
+        let showmode_label = gtk::Label::new(None);
+        let showcmd_label = gtk::Label::new(None);
+        let ruler_label = gtk::Label::new(None);
+
+        let status = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        status.pack_start(&showmode_label, false, false, 0);
+        status.pack_end(&ruler_label, false, false, 0);
+        status.pack_end(&showcmd_label, false, false, 0);
+
+        let box_ = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        box_.pack_start(&history_view.widget(), false, false, 0);
+        box_.pack_start(&view.widget(), false, false, 0);
+        box_.pack_start(&status, false, false, 0);
+        box_.set_halign(gtk::Align::End);
+        box_.set_valign(gtk::Align::End);
+
+        add_css_provider!(
+            &css_provider,
+            box_,
+            status,
+            showmode_label,
+            showcmd_label,
+            ruler_label
+        );
+
+        parent.add_overlay(&box_);
+
+        parent.connect_size_allocate(clone!(box_ => move |_, alloc| {
+            // Make sure we'll fit to the available space.
+            box_.set_size_request(MAX_WIDTH.min(alloc.width), -1);
+        }));
+
+        Messages {
+            css_provider,
+            box_,
+            view,
+            history_view,
+            status,
+            showmode_label,
+            showcmd_label,
+            ruler_label,
+            messages: vec![],
+            history: vec![],
+            showmode: vec![],
+            showcmd: vec![],
+            ruler: vec![],
+            colors: MessagesColors::default(),
+            font: Font::default(),
+        }
+    }
+
+    pub fn show(&mut self, msg: MsgShow, hl_defs: &HlDefs) {
+        // Without the grid, there isn't really a "prompt" to show.
+        if msg.kind == "return_prompt" {
+            return;
+        }
+
+        if msg.replace_last {
+            self.messages.pop();
+        }
+        self.messages.push(msg.content);
+
+        self.view.set_messages(&self.messages, hl_defs);
+        self.update_visibility();
+    }
+
+    /// Clears the messages. The history is left alone; nvim hides it with
+    /// an empty `msg_history_show` or `msg_history_clear`.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.update_visibility();
+    }
+
+    /// Replaces the history. An empty history hides the history view.
+    pub fn show_history(&mut self, history: MsgHistoryShow, hl_defs: &HlDefs) {
+        self.history = history
+            .entries
+            .into_iter()
+            .map(|(_, content)| content)
+            .collect();
+
+        self.history_view.set_messages(&self.history, hl_defs);
+        self.update_visibility();
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.update_visibility();
+    }
+
+    pub fn set_showmode(&mut self, content: Content, hl_defs: &HlDefs) {
+        self.showmode_label
+            .set_markup(&content_markup(&content, hl_defs));
+        self.showmode = content;
+        self.update_visibility();
+    }
+
+    pub fn set_showcmd(&mut self, content: Content, hl_defs: &HlDefs) {
+        self.showcmd_label
+            .set_markup(&content_markup(&content, hl_defs));
+        self.showcmd = content;
+        self.update_visibility();
+    }
+
+    pub fn set_ruler(&mut self, content: Content, hl_defs: &HlDefs) {
+        self.ruler_label
+            .set_markup(&content_markup(&content, hl_defs));
+        self.ruler = content;
+        self.update_visibility();
+    }
+
+    /// Hides everything, e.g. when `ext_messages` is disabled.
+    pub fn hide(&mut self) {
+        self.messages.clear();
+        self.history.clear();
+        self.showmode.clear();
+        self.showcmd.clear();
+        self.ruler.clear();
+        self.update_visibility();
+    }
+
+    /// Shows the parts that have something to show.
+    fn update_visibility(&self) {
+        let is_empty =
+            |content: &Content| content.iter().all(|(_, text)| text.is_empty());
+
+        let show_messages = !self.messages.is_empty();
+        let show_history = !self.history.is_empty();
+        let show_status = !is_empty(&self.showmode)
+            || !is_empty(&self.showcmd)
+            || !is_empty(&self.ruler);
+
+        self.view.widget().set_visible(show_messages);
+        self.history_view.widget().set_visible(show_history);
+        self.status.set_visible(show_status);
+        self.box_
+            .set_visible(show_messages || show_history || show_status);
+    }
+
+    /// Re-renders everything, e.g. after the highlights have changed.
+    fn redraw(&self, hl_defs: &HlDefs) {
+        self.view.set_messages(&self.messages, hl_defs);
+        self.history_view.set_messages(&self.history, hl_defs);
+        self.showmode_label
+            .set_markup(&content_markup(&self.showmode, hl_defs));
+        self.showcmd_label
+            .set_markup(&content_markup(&self.showcmd, hl_defs));
+        self.ruler_label
+            .set_markup(&content_markup(&self.ruler, hl_defs));
+    }
+
+    pub fn set_colors(&mut self, hl_defs: &HlDefs) {
+        let normal = hl_defs
+            .get_hl_group(&HlGroup::Cmdline)
+            .cloned()
+            .unwrap_or_default();
+        self.colors = MessagesColors {
+            fg: normal.foreground,
+            bg: normal.background,
+            border: hl_defs
+                .get_hl_group(&HlGroup::MsgSeparator)
+                .cloned()
+                .unwrap_or_default()
+                .foreground,
+        };
+
+        self.set_styles(hl_defs);
+        self.redraw(hl_defs);
+    }
+
+    pub fn set_font(&mut self, font: Font, hl_defs: &HlDefs) {
+        self.font = font;
+        self.set_styles(hl_defs);
+    }
+
+    pub fn set_line_space(&self, space: i64) {
+        self.view.set_line_space(space);
+        self.history_view.set_line_space(space);
+    }
+
+    fn set_styles(&self, hl_defs: &HlDefs) {
+        if gtk::get_minor_version() < 20 {
+            self.set_styles_pre20(hl_defs);
+        } else {
+            self.set_styles_post20(hl_defs);
+        }
+    }
+
+    fn set_styles_post20(&self, hl_defs: &HlDefs) {
+        let css = format!(
+            "{font_wild}
+
+            box {{
+                background: #{bg};
+                padding: 5px;
+                border-top: 1px solid #{border};
+            }}
+
+            box > box {{
+                padding: 0;
+                border: none;
+            }}
+
+            frame > border {{
+                border: none;
+            }}
+
+            label, textview, text {{
+                color: #{fg};
+                background: #{bg};
+            }}",
+            font_wild = self.font.as_wild_css(FontUnit::Point),
+            fg = self.colors.fg.unwrap_or(hl_defs.default_fg).to_hex(),
+            bg = self.colors.bg.unwrap_or(hl_defs.default_bg).to_hex(),
+            border = self.colors.border.unwrap_or(hl_defs.default_fg).to_hex(),
+        );
+        CssProviderExt::load_from_data(&self.css_provider, css.as_bytes())
+            .unwrap();
+    }
+
+    fn set_styles_pre20(&self, hl_defs: &HlDefs) {
+        let css = format!(
+            "{font_wild}
+
+            GtkBox {{
+                background: #{bg};
+                padding: 5px;
+                border-top: 1px solid #{border};
+            }}
+
+            GtkBox > GtkBox {{
+                padding: 0;
+                border: none;
+            }}
+
+            GtkFrame {{
+                border: none;
+                border-radius: 0;
+            }}
+
+            GtkLabel, GtkTextView {{
+                color: #{fg};
+                background: #{bg};
+            }}",
+            font_wild = self.font.as_wild_css(FontUnit::Pixel),
+            fg = self.colors.fg.unwrap_or(hl_defs.default_fg).to_hex(),
+            bg = self.colors.bg.unwrap_or(hl_defs.default_bg).to_hex(),
+            border = self.colors.border.unwrap_or(hl_defs.default_fg).to_hex(),
+        );
+        CssProviderExt::load_from_data(&self.css_provider, css.as_bytes())
+            .unwrap();
+    }
+}
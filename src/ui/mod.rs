@@ -52,13 +52,27 @@ pub mod color;
 mod common;
 #[cfg(feature = "libwebkit2gtk")]
 mod cursor_tooltip;
+mod error_dialog;
 mod font;
 mod grid;
+mod messages;
 mod popupmenu;
+mod prompt;
+mod scrollbar;
+mod session_ended;
 mod state;
 mod tabline;
+#[cfg(test)]
+mod tests;
 #[allow(clippy::module_inception)]
 mod ui;
 mod wildmenu;
 mod window;
-pub use self::ui::UI;
+pub use self::common::{escape_filename, escape_rtp_item};
+pub use self::error_dialog::show_error;
+pub use self::font::Font;
+pub use self::grid::HeadlessGrid;
+pub use self::ui::{
+    open_files, SessionError, SessionStarter, NEW_WINDOW_ACTION, OPEN_HINT_TAB,
+    UI,
+};
@@ -309,6 +309,13 @@ impl Popupmenu {
         self.show_menu_on_all_items = b;
     }
 
+    /// Sets the translucency of the popupmenu, from 0 (opaque) to 100
+    /// (fully transparent). Same as `'pumblend'`.
+    pub fn set_blend(&self, blend: i64) {
+        let blend = blend.max(0).min(100) as f64 / 100.0;
+        self.box_.set_opacity(1.0 - blend);
+    }
+
     #[allow(unused)]
     pub fn is_above_anchor(&self) -> bool {
         self.scrolled_list.get_child().unwrap().get_valign() == gtk::Align::End
@@ -0,0 +1,360 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use log::error;
+
+use crate::nvim_gio::GioNeovim;
+use crate::ui::common::spawn_local;
+
+/// Labels for the choices of `:s///c`.
+const SUBSTITUTE_CHOICES: &[(&str, &str)] = &[
+    ("y", "Yes"),
+    ("n", "No"),
+    ("a", "All"),
+    ("q", "Quit"),
+    ("l", "Last"),
+];
+
+#[derive(Debug, PartialEq)]
+struct Choice {
+    /// Label without the hotkey markers (e.g. `Yes` for `[Y]es`).
+    label: String,
+    /// Key to send to nvim when this choice is selected.
+    key: String,
+    /// Byte offset of the hotkey in `label`.
+    hotkey_pos: usize,
+    default: bool,
+}
+
+impl Choice {
+    /// Label with the hotkey as gtk's mnemonic (e.g. `_Yes`).
+    fn mnemonic(&self) -> String {
+        let (head, tail) = self.label.split_at(self.hotkey_pos);
+        format!("{}_{}", head.replace('_', "__"), tail.replace('_', "__"))
+    }
+}
+
+/// Parses a single choice of a `confirm()` prompt, e.g. `[Y]es` or
+/// `Save (A)ll`. Brackets mark the default choice.
+fn parse_choice(s: &str) -> Option<Choice> {
+    let start = s.find(|c| c == '[' || c == '(')?;
+    let close = if s[start..].starts_with('[') {
+        ']'
+    } else {
+        ')'
+    };
+
+    let mut chars = s[start + 1..].chars();
+    let key = chars.next()?;
+    if chars.next()? != close {
+        return None;
+    }
+
+    let end = start + 1 + key.len_utf8();
+    Some(Choice {
+        label: format!("{}{}{}", &s[..start], key, &s[end + 1..]),
+        key: key.to_lowercase().to_string(),
+        hotkey_pos: start,
+        default: close == ']',
+    })
+}
+
+/// Parses the message of a `confirm` kind `msg_show`. Returns the message
+/// and the choices, which nvim puts on the last line (e.g.
+/// `[Y]es, (N)o, (C)ancel: `).
+fn parse_confirm(text: &str) -> Option<(String, Vec<Choice>)> {
+    let text = text.trim();
+    let (message, choices) = match text.rfind('\n') {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => ("", text),
+    };
+
+    let choices = choices
+        .trim_end_matches(':')
+        .split(", ")
+        .map(parse_choice)
+        .collect::<Option<Vec<_>>>()?;
+
+    Some((message.trim().to_string(), choices))
+}
+
+/// Parses the message of a `confirm_sub` kind `msg_show`, e.g.
+/// `replace with bar (y/n/a/q/l/^E/^Y)?`. The scrolling choices (`^E`, `^Y`)
+/// are left out.
+fn parse_confirm_sub(text: &str) -> Option<(String, Vec<Choice>)> {
+    let text = text.trim().trim_end_matches('?');
+    let start = text.rfind('(')?;
+    let keys = text[start + 1..].strip_suffix(')')?;
+
+    let choices = keys
+        .split('/')
+        .filter_map(|key| {
+            SUBSTITUTE_CHOICES.iter().find(|(k, _)| *k == key).map(
+                |(key, label)| Choice {
+                    label: label.to_string(),
+                    key: key.to_string(),
+                    hotkey_pos: 0,
+                    default: false,
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if choices.is_empty() {
+        return None;
+    }
+
+    Some((text[..start].trim().to_string(), choices))
+}
+
+/// If `text` is cmdline content masked by `inputsecret()`.
+fn is_masked(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c == '*')
+}
+
+fn send_input(nvim: GioNeovim, keys: String) {
+    spawn_local(async move {
+        if let Err(err) = nvim.input(&keys).await {
+            error!("Failed to send prompt answer: {}", err);
+        }
+    });
+}
+
+/// Native dialogs for nvim's `confirm()` and `input()` prompts.
+pub struct Prompt {
+    parent: gtk::ApplicationWindow,
+    nvim: GioNeovim,
+
+    confirm_dialog: Rc<RefCell<Option<gtk::MessageDialog>>>,
+    input_dialog: Rc<RefCell<Option<(gtk::MessageDialog, gtk::Entry)>>>,
+    /// Set when the `input()` prompt is answered, until nvim hides the
+    /// cmdline. Nvim might redraw the prompt while it's processing our
+    /// answer, and we don't want to open another dialog for that.
+    input_answered: Rc<Cell<bool>>,
+}
+
+impl Prompt {
+    pub fn new(parent: &gtk::ApplicationWindow, nvim: GioNeovim) -> Self {
+        Prompt {
+            parent: parent.clone(),
+            nvim,
+            confirm_dialog: Rc::new(RefCell::new(None)),
+            input_dialog: Rc::new(RefCell::new(None)),
+            input_answered: Rc::new(Cell::new(false)),
+        }
+    }
+
+    fn new_dialog(&self, message: &str) -> gtk::MessageDialog {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.parent),
+            gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::None,
+            message,
+        );
+        dialog.set_title("Gnvim");
+
+        dialog
+    }
+
+    /// Shows a dialog for `msg_show`, if it is a confirm prompt (e.g.
+    /// `confirm()`, `:s///c` or the swap file prompt). Returns true if the
+    /// message was handled.
+    pub fn confirm(&mut self, kind: &str, content: &[(u64, String)]) -> bool {
+        let text: String = content.iter().map(|(_, t)| t.as_str()).collect();
+        let (message, choices) = match kind {
+            "confirm" => parse_confirm(&text),
+            "confirm_sub" => parse_confirm_sub(&text),
+            _ => None,
+        }
+        .unwrap_or_default();
+
+        if choices.is_empty() {
+            return false;
+        }
+
+        self.close_confirm();
+
+        let dialog = self.new_dialog(&message);
+        for (i, choice) in choices.iter().enumerate() {
+            let res = gtk::ResponseType::Other(i as u16);
+            dialog.add_button(&choice.mnemonic(), res);
+            if choice.default {
+                dialog.set_default_response(res);
+            }
+        }
+
+        let keys = choices
+            .into_iter()
+            .map(|choice| choice.key.replace("<", "<lt>"))
+            .collect::<Vec<_>>();
+        let nvim = self.nvim.clone();
+        let confirm_dialog = self.confirm_dialog.clone();
+        dialog.connect_response(move |dialog, res| {
+            let keys = match res {
+                gtk::ResponseType::Other(i) => keys.get(i as usize).cloned(),
+                _ => None,
+            };
+
+            confirm_dialog.borrow_mut().take();
+            dialog.destroy();
+
+            // Closing the dialog cancels the prompt.
+            send_input(nvim.clone(), keys.unwrap_or_else(|| "<Esc>".into()));
+        });
+
+        dialog.show_all();
+        self.confirm_dialog.replace(Some(dialog));
+
+        true
+    }
+
+    /// Shows a dialog for `cmdline_show`, if it is an `input()` prompt.
+    /// Returns true if the cmdline was handled.
+    ///
+    /// The text is edited in the dialog and mirrored to nvim's cmdline, so
+    /// the prompt's `completion` and cmdline mappings (e.g. `<C-r>`) aren't
+    /// available. Nvim tells us about `inputsecret()` only by echoing the
+    /// text masked, so the dialog hides its text after the first key. A
+    /// masked default can't be edited in the dialog, so such prompt is left
+    /// to the cmdline.
+    ///
+    /// * `prompt` - Prompt of the cmdline.
+    /// * `text` - Current text of the cmdline (e.g. `input()`'s default).
+    pub fn input(&mut self, prompt: &str, text: &str) -> bool {
+        if self.input_answered.get() {
+            return true;
+        }
+
+        if let Some((_, ref entry)) = *self.input_dialog.borrow() {
+            if is_masked(text) && entry.get_text().as_str() != text {
+                entry.set_visibility(false);
+            }
+            return true;
+        }
+
+        if is_masked(text) {
+            return false;
+        }
+
+        let dialog = self.new_dialog(prompt.trim());
+        dialog.add_button("_Cancel", gtk::ResponseType::Cancel);
+        dialog.add_button("_OK", gtk::ResponseType::Ok);
+        dialog.set_default_response(gtk::ResponseType::Ok);
+
+        let entry = gtk::Entry::new();
+        entry.set_text(text);
+        entry.set_activates_default(true);
+        if let Some(area) = dialog
+            .get_message_area()
+            .and_then(|w| w.downcast::<gtk::Box>().ok())
+        {
+            area.pack_start(&entry, false, false, 0);
+        }
+
+        // Keep nvim's cmdline up to date, so we'll see if it masks the text.
+        let nvim = self.nvim.clone();
+        entry.connect_changed(move |entry| {
+            send_input(
+                nvim.clone(),
+                format!(
+                    "<C-e><C-u>{}",
+                    entry.get_text().as_str().replace("<", "<lt>")
+                ),
+            );
+        });
+
+        let nvim = self.nvim.clone();
+        let input_dialog = self.input_dialog.clone();
+        let input_answered = self.input_answered.clone();
+        let response_entry = entry.clone();
+        dialog.connect_response(move |dialog, res| {
+            let keys = if res == gtk::ResponseType::Ok {
+                // Replace whatever is on the cmdline with our text.
+                format!(
+                    "<C-e><C-u>{}<CR>",
+                    response_entry.get_text().as_str().replace("<", "<lt>")
+                )
+            } else {
+                String::from("<Esc>")
+            };
+
+            input_answered.set(true);
+            input_dialog.borrow_mut().take();
+            dialog.destroy();
+
+            send_input(nvim.clone(), keys);
+        });
+
+        dialog.show_all();
+        self.input_dialog.replace(Some((dialog, entry)));
+
+        true
+    }
+
+    /// Nvim hid the cmdline, so the `input()` prompt is done with.
+    pub fn cmdline_hidden(&mut self) {
+        self.input_answered.set(false);
+        if let Some((dialog, _)) = self.input_dialog.borrow_mut().take() {
+            dialog.destroy();
+        }
+    }
+
+    fn close_confirm(&mut self) {
+        if let Some(dialog) = self.confirm_dialog.borrow_mut().take() {
+            dialog.destroy();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_confirm() {
+        let (message, choices) = parse_confirm(
+            "\nSave changes to \"foo\"?\n[Y]es, (N)o, Save (A)ll, (C)ancel: ",
+        )
+        .unwrap();
+
+        assert_eq!(message, "Save changes to \"foo\"?");
+        assert_eq!(
+            choices.iter().map(Choice::mnemonic).collect::<Vec<_>>(),
+            vec!["_Yes", "_No", "Save _All", "_Cancel"]
+        );
+        assert_eq!(
+            choices.iter().map(|c| c.key.as_str()).collect::<Vec<_>>(),
+            vec!["y", "n", "a", "c"]
+        );
+        assert_eq!(
+            choices.iter().map(|c| c.default).collect::<Vec<_>>(),
+            vec![true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_parse_confirm_not_a_prompt() {
+        assert_eq!(parse_confirm("Hello\nworld"), None);
+    }
+
+    #[test]
+    fn test_is_masked() {
+        assert!(is_masked("***"));
+        assert!(!is_masked(""));
+        assert!(!is_masked("*a*"));
+    }
+
+    #[test]
+    fn test_parse_confirm_sub() {
+        let (message, choices) =
+            parse_confirm_sub("replace with bar (y/n/a/q/l/^E/^Y)?").unwrap();
+
+        assert_eq!(message, "replace with bar");
+        assert_eq!(
+            choices.iter().map(|c| c.label.as_str()).collect::<Vec<_>>(),
+            vec!["Yes", "No", "All", "Quit", "Last"]
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use log::error;
+use rmpv::Value;
+
+use crate::nvim_bridge::WinViewport;
+use crate::nvim_gio::GioNeovim;
+use crate::ui::common::spawn_local;
+
+/// Time (in ms) after which an unused scrollbar fades out.
+const IDLE_TIMEOUT: u32 = 1000;
+
+/// Expression for the current window's viewport, in the order of
+/// `win_viewport`'s window, topline, botline and line count.
+const VIEWPORT_EXPR: &str =
+    "[win_getid(), line('w0') - 1, line('w$'), line('$')]";
+
+/// Thin overlay scrollbar for a nvim window. Shows the window's viewport
+/// (from `win_viewport`) and scrolls the window when dragged.
+pub struct Scrollbar {
+    view: View,
+    nvim: GioNeovim,
+
+    /// Handle of the nvim window that we're showing.
+    win: Rc<RefCell<Value>>,
+    /// Latest `topline` and `botline` from nvim.
+    lines: Rc<Cell<(u64, u64)>>,
+    /// If we're asking nvim for the current window's viewport.
+    querying: Rc<Cell<bool>>,
+    /// If the viewport might have changed since we asked for it.
+    stale: Rc<Cell<bool>>,
+}
+
+/// The scrollbar widget and its state, shared with the signal handlers and
+/// the line count requests.
+#[derive(Clone)]
+struct View {
+    scrollbar: gtk::Scrollbar,
+    adjustment: gtk::Adjustment,
+
+    /// Set while we're applying nvim's viewport, so we don't send it back.
+    updating: Rc<Cell<bool>>,
+    /// If the user is interacting with the scrollbar (hovering or dragging).
+    active: Rc<Cell<bool>>,
+    /// Source for fading out the scrollbar.
+    idle_source: Rc<RefCell<Option<glib::SourceId>>>,
+}
+
+impl Scrollbar {
+    pub fn new(nvim: GioNeovim) -> Self {
+        let adjustment = gtk::Adjustment::new(0.0, 0.0, 1.0, 1.0, 1.0, 1.0);
+        let scrollbar =
+            gtk::Scrollbar::new(gtk::Orientation::Vertical, Some(&adjustment));
+        scrollbar.set_halign(gtk::Align::End);
+        scrollbar.set_no_show_all(true);
+        scrollbar.add_events(
+            gdk::EventMask::ENTER_NOTIFY_MASK
+                | gdk::EventMask::LEAVE_NOTIFY_MASK,
+        );
+
+        let css_provider = gtk::CssProvider::new();
+        let css = if gtk::get_minor_version() < 20 {
+            "GtkScrollbar {
+                transition: opacity 300ms ease-out;
+            }
+
+            GtkScrollbar.idle {
+                opacity: 0;
+            }"
+        } else {
+            "scrollbar {
+                background: transparent;
+                border: none;
+                transition: opacity 300ms ease-out;
+            }
+
+            scrollbar.idle {
+                opacity: 0;
+            }"
+        };
+        CssProviderExt::load_from_data(&css_provider, css.as_bytes()).unwrap();
+        add_css_provider!(&css_provider, scrollbar);
+
+        let style = scrollbar.get_style_context();
+        // Thin scrollbar, like the ones in gtk's overlay scrolling.
+        style.add_class("overlay-indicator");
+        style.add_class("idle");
+
+        let win = Rc::new(RefCell::new(Value::Nil));
+        let updating = Rc::new(Cell::new(false));
+        let active = Rc::new(Cell::new(false));
+        let idle_source = Rc::new(RefCell::new(None));
+
+        adjustment.connect_value_changed(clone!(nvim, win, updating => move |adj| {
+            if updating.get() {
+                return;
+            }
+
+            let win = win.borrow().clone();
+            if win == Value::Nil {
+                return;
+            }
+
+            // `topline` is 1-based for winrestview().
+            let topline = adj.get_value().round() as u64 + 1;
+            let nvim = nvim.clone();
+            spawn_local(async move {
+                let cmd = format!("call winrestview({{'topline': {}}})", topline);
+                if let Err(err) = nvim
+                    .call_function("win_execute", vec![win, cmd.into()])
+                    .await
+                {
+                    error!("Failed to scroll window: {}", err);
+                }
+            });
+        }));
+
+        scrollbar.connect_enter_notify_event(
+            clone!(active, idle_source => move |scrollbar, _| {
+                active.set(true);
+                wake(scrollbar, &active, &idle_source);
+                Inhibit(false)
+            }),
+        );
+
+        scrollbar.connect_leave_notify_event(
+            clone!(active, idle_source => move |scrollbar, _| {
+                active.set(false);
+                wake(scrollbar, &active, &idle_source);
+                Inhibit(false)
+            }),
+        );
+
+        Scrollbar {
+            view: View {
+                scrollbar,
+                adjustment,
+                updating,
+                active,
+                idle_source,
+            },
+            nvim,
+            win,
+            lines: Rc::new(Cell::new((0, 0))),
+            querying: Rc::new(Cell::new(false)),
+            stale: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn widget(&self) -> gtk::Widget {
+        self.view.scrollbar.clone().upcast()
+    }
+
+    /// Updates the scrollbar to match `viewport`. Hidden if the whole buffer
+    /// is visible. Older nvims don't tell the buffer's length, in which case
+    /// it's asked separately.
+    pub fn set_viewport(&self, viewport: &WinViewport) {
+        self.win.replace(viewport.win.clone());
+        self.lines.set((viewport.topline, viewport.botline));
+
+        if let Some(line_count) = viewport.line_count {
+            self.view
+                .configure(viewport.topline, viewport.botline, line_count);
+            return;
+        }
+
+        let nvim = self.nvim.clone();
+        let win = viewport.win.clone();
+        let lines = self.lines.clone();
+        let view = self.view.clone();
+        spawn_local(async move {
+            match nvim
+                .call_function("line", vec!["$".into(), win])
+                .await
+                .map(|count| count.as_u64())
+            {
+                // Apply the latest viewport, it might have changed meanwhile.
+                Ok(Some(line_count)) => {
+                    let (topline, botline) = lines.get();
+                    view.configure(topline, botline, line_count);
+                }
+                Ok(None) => error!("Unexpected line count"),
+                Err(err) => error!("Failed to get line count: {}", err),
+            }
+        });
+    }
+
+    /// Updates the scrollbar to match the viewport of nvim's current window.
+    /// Without multigrid nvim doesn't send `win_viewport`, so the viewport
+    /// is asked after each redraw instead. Only one request is on its way at
+    /// a time.
+    pub fn query_viewport(&self) {
+        if self.querying.replace(true) {
+            self.stale.set(true);
+            return;
+        }
+
+        let nvim = self.nvim.clone();
+        let win = self.win.clone();
+        let querying = self.querying.clone();
+        let stale = self.stale.clone();
+        let view = self.view.clone();
+        spawn_local(async move {
+            loop {
+                stale.set(false);
+
+                match nvim.eval(VIEWPORT_EXPR).await {
+                    Ok(Value::Array(viewport)) => {
+                        let lines = viewport
+                            .iter()
+                            .skip(1)
+                            .filter_map(Value::as_u64)
+                            .collect::<Vec<_>>();
+                        if let [topline, botline, line_count] = lines[..] {
+                            win.replace(viewport[0].clone());
+                            view.configure(topline, botline, line_count);
+                        } else {
+                            error!("Unexpected viewport: {:?}", viewport);
+                        }
+                    }
+                    Ok(viewport) => {
+                        error!("Unexpected viewport: {}", viewport)
+                    }
+                    Err(err) => error!("Failed to get viewport: {}", err),
+                }
+
+                if !stale.get() {
+                    break;
+                }
+            }
+
+            querying.set(false);
+        });
+    }
+
+    /// Hides the scrollbar, e.g. when the windows have scrollbars of their
+    /// own.
+    pub fn hide(&self) {
+        self.win.replace(Value::Nil);
+        self.view.scrollbar.hide();
+    }
+}
+
+impl View {
+    /// Shows lines from `topline` to `botline` of the window's buffer, which
+    /// has `line_count` lines.
+    fn configure(&self, topline: u64, botline: u64, line_count: u64) {
+        let page = botline.saturating_sub(topline) as f64;
+        let topline = topline as f64;
+        let upper = (line_count as f64).max(topline + page);
+
+        if page >= upper {
+            self.scrollbar.hide();
+            return;
+        }
+
+        let changed = (self.adjustment.get_value() - topline).abs()
+            > f64::EPSILON
+            || (self.adjustment.get_upper() - upper).abs() > f64::EPSILON;
+
+        self.updating.set(true);
+        self.adjustment.configure(
+            // While the user is using the scrollbar, they are in control of
+            // the value.
+            if self.active.get() {
+                self.adjustment.get_value()
+            } else {
+                topline
+            },
+            0.0,
+            upper,
+            1.0,
+            page,
+            page,
+        );
+        self.updating.set(false);
+
+        self.scrollbar.show();
+        if changed {
+            wake(&self.scrollbar, &self.active, &self.idle_source);
+        }
+    }
+}
+
+/// Shows the scrollbar and fades it out after `IDLE_TIMEOUT`, unless it is
+/// `active`.
+fn wake(
+    scrollbar: &gtk::Scrollbar,
+    active: &Rc<Cell<bool>>,
+    idle_source: &Rc<RefCell<Option<glib::SourceId>>>,
+) {
+    if let Some(id) = idle_source.borrow_mut().take() {
+        glib::source::source_remove(id);
+    }
+
+    scrollbar.get_style_context().remove_class("idle");
+
+    if active.get() {
+        return;
+    }
+
+    let scrollbar = scrollbar.downgrade();
+    let id = gtk::timeout_add(
+        IDLE_TIMEOUT,
+        clone!(idle_source => move || {
+            // The source is done once we return.
+            idle_source.borrow_mut().take();
+
+            if let Some(scrollbar) = scrollbar.upgrade() {
+                scrollbar.get_style_context().add_class("idle");
+            }
+
+            Continue(false)
+        }),
+    );
+    idle_source.replace(Some(id));
+}
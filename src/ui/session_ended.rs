@@ -0,0 +1,80 @@
+use gtk::prelude::*;
+
+/// View that is shown in the main window once nvim has gone away.
+pub struct SessionEnded {
+    container: gtk::Box,
+    /// Tells why the session ended.
+    reason: gtk::Label,
+    /// Button to start a new session.
+    restart: gtk::Button,
+    /// Button to close the window.
+    quit: gtk::Button,
+}
+
+impl SessionEnded {
+    /// Creates new view.
+    ///
+    /// * `reconnect` - If the session is attached to an external nvim. Only
+    ///                 affects the restart button's label.
+    pub fn new(reconnect: bool) -> Self {
+        let container = gtk::Box::new(gtk::Orientation::Vertical, 12);
+        container.set_halign(gtk::Align::Center);
+        container.set_valign(gtk::Align::Center);
+
+        let title = gtk::Label::new(None);
+        title.set_markup("<big><b>Session ended</b></big>");
+
+        let reason = gtk::Label::new(None);
+        reason.set_line_wrap(true);
+        reason.set_selectable(true);
+
+        let restart = gtk::Button::with_label(if reconnect {
+            "Reconnect"
+        } else {
+            "Restart nvim"
+        });
+        let quit = gtk::Button::with_label("Close");
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.set_halign(gtk::Align::Center);
+        buttons.pack_start(&quit, false, false, 0);
+        buttons.pack_start(&restart, false, false, 0);
+
+        container.pack_start(&title, false, false, 0);
+        container.pack_start(&reason, false, false, 0);
+        container.pack_start(&buttons, false, false, 0);
+
+        SessionEnded {
+            container,
+            reason,
+            restart,
+            quit,
+        }
+    }
+
+    pub fn widget(&self) -> gtk::Widget {
+        self.container.clone().upcast()
+    }
+
+    /// Sets the text explaining why the session ended and makes the buttons
+    /// clickable again.
+    pub fn set_reason(&self, reason: &str) {
+        self.reason.set_text(reason);
+        self.restart.set_sensitive(true);
+        self.container.show_all();
+    }
+
+    /// Marks that a new session is being started.
+    pub fn set_starting(&self) {
+        self.reason.set_text("Starting...");
+        self.restart.set_sensitive(false);
+    }
+
+    pub fn connect_restart<F: Fn() + 'static>(&self, f: F) {
+        self.restart.connect_clicked(move |_| f());
+    }
+
+    pub fn connect_quit<F: Fn() + 'static>(&self, f: F) {
+        self.quit.connect_clicked(move |_| f());
+    }
+}
@@ -1,7 +1,10 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use gdk::WindowExt;
+use gio::ActionGroupExt;
 use gtk::prelude::*;
 
 use log::{debug, error, warn};
@@ -11,20 +14,24 @@ use crate::nvim_bridge::{
     CmdlineBlockAppend, CmdlineBlockShow, CmdlinePos, CmdlineShow,
     CmdlineSpecialChar, DefaultColorsSet, GnvimEvent, GridCursorGoto,
     GridLineSegment, GridResize, GridScroll, HlAttrDefine, HlGroupSet,
-    ModeChange, ModeInfo, ModeInfoSet, MsgSetPos, Notify, OptionSet,
-    PopupmenuShow, RedrawEvent, TablineUpdate, WindowExternalPos,
-    WindowFloatPos, WindowPos,
+    ModeChange, ModeInfo, ModeInfoSet, MsgHistoryShow, MsgSetPos, MsgShow,
+    Notify, OptionSet, PopupmenuShow, RedrawEvent, TablineUpdate, WinViewport,
+    WindowExternalPos, WindowFloatPos, WindowPos,
 };
 use crate::nvim_gio::GioNeovim;
 use crate::ui::cmdline::Cmdline;
 use crate::ui::color::{HlDefs, HlGroup};
-use crate::ui::common::spawn_local;
+use crate::ui::common::{display_path, spawn_local};
 #[cfg(feature = "libwebkit2gtk")]
 use crate::ui::cursor_tooltip::{CursorTooltip, Gravity};
 use crate::ui::font::Font;
 use crate::ui::grid::{Grid, GridMetrics};
+use crate::ui::messages::Messages;
 use crate::ui::popupmenu::Popupmenu;
+use crate::ui::prompt::Prompt;
+use crate::ui::scrollbar::Scrollbar;
 use crate::ui::tabline::Tabline;
+use crate::ui::ui::{set_subtitle, NEW_WINDOW_ACTION, OPEN_ACTION};
 use crate::ui::window::{MsgWindow, Window};
 
 pub(crate) type Windows = HashMap<i64, Window>;
@@ -62,6 +69,11 @@ pub(crate) struct UIState {
     pub popupmenu: Popupmenu,
     pub cmdline: Cmdline,
     pub tabline: Tabline,
+    pub messages: Messages,
+    pub prompt: Prompt,
+    /// Scrollbar for the base grid. Shows the viewport of grid 1, which
+    /// without multigrid is the current window.
+    pub scrollbar: Scrollbar,
     #[cfg(feature = "libwebkit2gtk")]
     pub cursor_tooltip: CursorTooltip,
 
@@ -84,6 +96,19 @@ pub(crate) struct UIState {
     pub line_space: i64,
 
     pub enable_cursor_animations: bool,
+    /// If nvim's mouse support is enabled (`mouse_on`/`mouse_off`).
+    pub mouse_enabled: bool,
+    /// If the mouse pointer is hidden while typing (`'mousehide'`). Shared
+    /// with the key press handler.
+    pub mousehide: Rc<Cell<bool>>,
+    /// Nvim's current directory. Shared with the main window, which opens
+    /// dropped files relative to it.
+    pub cwd: Rc<RefCell<Option<PathBuf>>>,
+    /// Font for double width characters (`'guifontwide'`).
+    pub font_wide: Option<Font>,
+    /// If nvim has `ext_multigrid` enabled. Without it, nvim draws
+    /// everything on the base grid and we ignore the window events.
+    pub multigrid: bool,
 }
 
 impl UIState {
@@ -100,7 +125,7 @@ impl UIState {
                 });
             }
             Notify::GnvimEvent(event) => match event {
-                Ok(event) => self.handle_gnvim_event(&event, nvim),
+                Ok(event) => self.handle_gnvim_event(window, &event, nvim),
                 Err(err) => {
                     let nvim = nvim.clone();
                     let msg = format!(
@@ -121,6 +146,19 @@ impl UIState {
         window.set_title(title);
     }
 
+    /// Shows `cwd` as the subtitle of the main window.
+    fn set_cwd(&mut self, window: &gtk::ApplicationWindow, cwd: &str) {
+        let cwd = PathBuf::from(cwd);
+        if self.cwd.borrow().as_ref() == Some(&cwd) {
+            return;
+        }
+
+        let home = glib::get_home_dir();
+        set_subtitle(window, Some(&display_path(&cwd, home.as_deref())));
+
+        self.cwd.replace(Some(cwd));
+    }
+
     fn grid_cursor_goto(
         &mut self,
         GridCursorGoto {
@@ -129,6 +167,10 @@ impl UIState {
             col,
         }: GridCursorGoto,
     ) {
+        if self.ignores_grid(grid_id) {
+            return;
+        }
+
         // Gird cursor goto sets the current cursor to grid_id,
         // so we'll need to handle that here...
         let grid = if grid_id != self.current_grid {
@@ -156,6 +198,10 @@ impl UIState {
         window: &gtk::ApplicationWindow,
         nvim: &GioNeovim,
     ) {
+        if self.ignores_grid(e.grid) {
+            return;
+        }
+
         let win = window.get_window().unwrap();
         if let Some(grid) = self.grids.get(&e.grid) {
             grid.resize(&win, e.width, e.height, &self.hl_defs);
@@ -193,17 +239,27 @@ impl UIState {
                 grid.set_mode(&mode);
             }
             grid.resize(&win, e.width, e.height, &self.hl_defs);
+            grid.set_mouse_enabled(self.mouse_enabled);
+            grid.set_font_wide(self.font_wide.clone());
             attach_grid_events(&grid, nvim.clone());
             self.grids.insert(e.grid, grid);
         }
     }
 
     fn grid_line(&mut self, line: GridLineSegment) {
+        if self.ignores_grid(line.grid) {
+            return;
+        }
+
         let grid = self.grids.get(&line.grid).unwrap();
         grid.put_line(line, &self.hl_defs);
     }
 
     fn grid_clear(&mut self, grid: &i64) {
+        if self.ignores_grid(*grid) {
+            return;
+        }
+
         let grid = self.grids.get(grid).unwrap();
         grid.clear(&self.hl_defs);
     }
@@ -226,6 +282,10 @@ impl UIState {
     }
 
     fn grid_scroll(&mut self, info: GridScroll, nvim: &GioNeovim) {
+        if self.ignores_grid(info.grid) {
+            return;
+        }
+
         let grid = self.grids.get(&info.grid).unwrap();
         grid.scroll(info.reg, info.rows, info.cols, &self.hl_defs);
 
@@ -265,35 +325,13 @@ impl UIState {
         self.hl_changed = true;
     }
 
-    fn hl_attr_define(&mut self, HlAttrDefine { id, hl }: HlAttrDefine) {
+    fn hl_attr_define(&mut self, HlAttrDefine { id, hl, info }: HlAttrDefine) {
         self.hl_defs.insert(id, hl);
+        self.hl_defs.insert_info(id, info);
     }
 
     fn hl_group_set(&mut self, evt: HlGroupSet) {
-        match evt.name.as_str() {
-            "Pmenu" => {
-                self.hl_defs.set_hl_group(HlGroup::Pmenu, evt.hl_id);
-                self.hl_defs.set_hl_group(HlGroup::Wildmenu, evt.hl_id)
-            }
-            "PmenuSel" => {
-                self.hl_defs.set_hl_group(HlGroup::PmenuSel, evt.hl_id);
-                self.hl_defs.set_hl_group(HlGroup::WildmenuSel, evt.hl_id)
-            }
-            "TabLine" => self.hl_defs.set_hl_group(HlGroup::Tabline, evt.hl_id),
-            "TabLineSel" => {
-                self.hl_defs.set_hl_group(HlGroup::TablineSel, evt.hl_id);
-                self.hl_defs.set_hl_group(HlGroup::CmdlineBorder, evt.hl_id)
-            }
-            "TabLineFill" => {
-                self.hl_defs.set_hl_group(HlGroup::TablineFill, evt.hl_id)
-            }
-            "Normal" => self.hl_defs.set_hl_group(HlGroup::Cmdline, evt.hl_id),
-            "MsgSeparator" => {
-                self.hl_defs.set_hl_group(HlGroup::MsgSeparator, evt.hl_id)
-            }
-            _ => None,
-        };
-
+        self.hl_defs.set_hl_group_name(evt.name, evt.hl_id);
         self.hl_changed = true;
     }
 
@@ -317,6 +355,13 @@ impl UIState {
 
                 self.resize_on_flush = Some(opts);
             }
+            OptionSet::GuiFontWide(font) => {
+                self.font_wide = Font::from_guifont(&font).ok();
+                for grid in self.grids.values() {
+                    grid.set_font_wide(self.font_wide.clone());
+                    grid.redraw(&self.hl_defs);
+                }
+            }
             OptionSet::LineSpace(val) => {
                 self.line_space = val;
                 let mut opts =
@@ -333,8 +378,7 @@ impl UIState {
                 self.resize_on_flush = Some(opts);
             }
             OptionSet::ExtTabline(enable) => {
-                let widget = self.tabline.get_widget();
-                widget_show(&widget, enable);
+                self.tabline.set_enabled(enable);
             }
             OptionSet::ExtCmdline(enable) => {
                 if !enable {
@@ -347,18 +391,55 @@ impl UIState {
                 // custom wildmenu. To "fix" this, the user needs to reopen the
                 // wildmenu/cmdline.
             }
+            OptionSet::ExtMessages(enable) => {
+                if !enable {
+                    self.messages.hide();
+                }
+            }
+            OptionSet::ExtMultigrid(enable) => self.set_multigrid(enable),
             OptionSet::ExtPopupmenu(_enable) => {
                 // Nothing to do... If the popupmenu is active at this point,
                 // nvim seems continue send the ext popupmenu messages until
                 // the popupmenu is closed. At least this is the case at the
                 // time of writing this feature.
             }
+            OptionSet::MouseHide(enable) => {
+                self.mousehide.set(enable);
+            }
+            OptionSet::PumBlend(blend) => {
+                self.popupmenu.set_blend(blend);
+            }
+            OptionSet::ShowTabline(val) => {
+                self.tabline.set_showtabline(val);
+            }
             OptionSet::NotSupported(name) => {
                 debug!("Not supported option set: {}", name);
             }
         }
     }
 
+    /// Rings the bell. If our window isn't focused, the window is marked
+    /// as urgent too.
+    ///
+    /// * `visual` - Flash the active grid instead of beeping.
+    fn bell(&self, window: &gtk::ApplicationWindow, visual: bool) {
+        if visual {
+            if let Some(grid) = self
+                .grids
+                .get(&self.current_grid)
+                .or_else(|| self.grids.get(&1))
+            {
+                grid.flash();
+            }
+        } else if let Some(win) = window.get_window() {
+            win.beep();
+        }
+
+        if !window.is_active() {
+            window.set_urgency_hint(true);
+        }
+    }
+
     fn mode_info_set(&mut self, ModeInfoSet { mode_info, .. }: ModeInfoSet) {
         self.mode_infos = mode_info;
     }
@@ -385,6 +466,11 @@ impl UIState {
             grid.flush(&self.hl_defs);
         }
 
+        // Nvim doesn't send `win_viewport` without multigrid.
+        if !self.multigrid {
+            self.scrollbar.query_viewport();
+        }
+
         if let Some(opts) = self.resize_on_flush.take() {
             let win = window.get_window().unwrap();
             for grid in self.grids.values() {
@@ -416,6 +502,7 @@ impl UIState {
             self.popupmenu.set_font(opts.font.clone(), &self.hl_defs);
             self.cmdline.set_font(opts.font.clone(), &self.hl_defs);
             self.tabline.set_font(opts.font.clone(), &self.hl_defs);
+            self.messages.set_font(opts.font.clone(), &self.hl_defs);
             #[cfg(feature = "libwebkit2gtk")]
             self.cursor_tooltip.set_font(opts.font.clone());
 
@@ -423,6 +510,7 @@ impl UIState {
             self.popupmenu
                 .set_line_space(opts.line_space, &self.hl_defs);
             self.tabline.set_line_space(opts.line_space, &self.hl_defs);
+            self.messages.set_line_space(opts.line_space);
         }
 
         if self.hl_changed {
@@ -430,6 +518,7 @@ impl UIState {
             self.tabline.set_colors(&self.hl_defs);
             self.cmdline.set_colors(&self.hl_defs);
             self.cmdline.wildmenu_set_colors(&self.hl_defs);
+            self.messages.set_colors(&self.hl_defs);
 
             let msgsep = self
                 .hl_defs
@@ -544,11 +633,25 @@ impl UIState {
     }
 
     fn cmdline_show(&mut self, cmdline_show: CmdlineShow) {
+        // `input()` prompt.
+        if cmdline_show.firstc.is_empty() && !cmdline_show.prompt.is_empty() {
+            let text: String = cmdline_show
+                .content
+                .iter()
+                .map(|(_, t)| t.as_str())
+                .collect();
+            if self.prompt.input(&cmdline_show.prompt, &text) {
+                self.cmdline.hide();
+                return;
+            }
+        }
+
         self.cmdline.show(cmdline_show, &self.hl_defs);
     }
 
     fn cmdline_hide(&mut self) {
         self.cmdline.hide();
+        self.prompt.cmdline_hidden();
     }
 
     fn cmdline_pos(&mut self, CmdlinePos { pos, level }: CmdlinePos) {
@@ -572,7 +675,23 @@ impl UIState {
         self.cmdline.hide_block();
     }
 
+    fn msg_show(&mut self, msg: MsgShow) {
+        if self.prompt.confirm(&msg.kind, &msg.content) {
+            return;
+        }
+
+        self.messages.show(msg, &self.hl_defs);
+    }
+
+    fn msg_history_show(&mut self, history: MsgHistoryShow) {
+        self.messages.show_history(history, &self.hl_defs);
+    }
+
     fn window_pos(&mut self, evt: WindowPos, nvim: &GioNeovim) {
+        if !self.multigrid {
+            return;
+        }
+
         let base_metrics = self.grids.get(&1).unwrap().get_grid_metrics();
         let x = evt.start_col as f64 * base_metrics.cell_width;
         let y = evt.start_row as f64 * base_metrics.cell_height;
@@ -630,11 +749,16 @@ impl UIState {
                     container,
                     &grid,
                     Some(css_provider),
+                    nvim.clone(),
                 )
             })
     }
 
     fn window_float_pos(&mut self, evt: WindowFloatPos, nvim: &GioNeovim) {
+        if !self.multigrid {
+            return;
+        }
+
         let (x_offset, y_offset) = self.get_float_anchor_pos(&evt);
 
         let anchor_metrics =
@@ -703,6 +827,10 @@ impl UIState {
         window: &gtk::ApplicationWindow,
         nvim: &GioNeovim,
     ) {
+        if !self.multigrid {
+            return;
+        }
+
         let parent_win = window.clone().upcast::<gtk::Window>();
         let grid_metrics = {
             let grid = self.grids.get(&evt.grid).unwrap();
@@ -738,6 +866,10 @@ impl UIState {
     }
 
     fn window_hide(&mut self, grid_id: i64) {
+        if !self.multigrid {
+            return;
+        }
+
         self.windows.get(&grid_id).unwrap().hide();
     }
 
@@ -748,7 +880,19 @@ impl UIState {
         }
     }
 
+    fn win_viewport(&mut self, evt: WinViewport) {
+        if let Some(window) = self.windows.get(&evt.grid) {
+            window.set_viewport(&evt);
+        } else if evt.grid == 1 {
+            self.scrollbar.set_viewport(&evt);
+        }
+    }
+
     fn msg_set_pos(&mut self, e: MsgSetPos) {
+        if !self.multigrid {
+            return;
+        }
+
         let base_grid = self.grids.get(&1).unwrap();
         let base_metrics = base_grid.get_grid_metrics();
         let grid = self.grids.get(&e.grid).unwrap();
@@ -756,6 +900,42 @@ impl UIState {
         self.msg_window.set_pos(&grid, e.row as f64, h, e.scrolled);
     }
 
+    /// If events for `grid` are ignored. Without multigrid, only the base
+    /// grid is rendered.
+    fn ignores_grid(&self, grid: i64) -> bool {
+        !self.multigrid && grid != 1
+    }
+
+    /// Switches between multigrid and single-grid mode. When switching to
+    /// single-grid mode, nvim redraws everything on the base grid, so the
+    /// other grids and their windows are dropped.
+    fn set_multigrid(&mut self, enable: bool) {
+        self.multigrid = enable;
+        if enable {
+            // Windows have scrollbars of their own.
+            self.scrollbar.hide();
+            return;
+        }
+
+        self.windows.clear();
+        self.msg_window.clear();
+        self.grids.retain(|id, _| *id == 1);
+
+        if self.current_grid != 1 {
+            self.current_grid = 1;
+            if let Some(grid) = self.grids.get(&1) {
+                grid.set_active(true);
+            }
+        }
+    }
+
+    fn set_mouse_enabled(&mut self, enabled: bool) {
+        self.mouse_enabled = enabled;
+        self.grids
+            .values()
+            .for_each(|g| g.set_mouse_enabled(enabled));
+    }
+
     fn enable_cursor_animations(&mut self, enable: bool) {
         self.enable_cursor_animations = enable;
         self.grids
@@ -773,6 +953,9 @@ impl UIState {
             RedrawEvent::SetTitle(evt) => {
                 evt.iter().for_each(|e| self.set_title(&window, e));
             }
+            RedrawEvent::Chdir(evt) => {
+                evt.iter().for_each(|e| self.set_cwd(&window, e));
+            }
             RedrawEvent::GridLine(evt) => {
                 evt.into_iter().for_each(|line| self.grid_line(line))
             }
@@ -857,10 +1040,37 @@ impl UIState {
             RedrawEvent::MsgSetPos(evt) => {
                 evt.into_iter().for_each(|e| self.msg_set_pos(e));
             }
+            RedrawEvent::WinViewport(evt) => {
+                evt.into_iter().for_each(|e| self.win_viewport(e));
+            }
+            RedrawEvent::MouseOn() => self.set_mouse_enabled(true),
+            RedrawEvent::MouseOff() => self.set_mouse_enabled(false),
+            RedrawEvent::Bell() => self.bell(window, false),
+            RedrawEvent::VisualBell() => self.bell(window, true),
+            RedrawEvent::MsgShow(evt) => {
+                evt.into_iter().for_each(|e| self.msg_show(e));
+            }
+            RedrawEvent::MsgClear() => self.messages.clear(),
+            RedrawEvent::MsgShowmode(evt) => evt
+                .into_iter()
+                .for_each(|e| self.messages.set_showmode(e, &self.hl_defs)),
+            RedrawEvent::MsgShowcmd(evt) => evt
+                .into_iter()
+                .for_each(|e| self.messages.set_showcmd(e, &self.hl_defs)),
+            RedrawEvent::MsgRuler(evt) => evt
+                .into_iter()
+                .for_each(|e| self.messages.set_ruler(e, &self.hl_defs)),
+            RedrawEvent::MsgHistoryShow(evt) => {
+                evt.into_iter().for_each(|e| self.msg_history_show(e));
+            }
+            RedrawEvent::MsgHistoryClear() => self.messages.clear_history(),
             RedrawEvent::Ignored(_) => (),
             RedrawEvent::Unknown(e) => {
                 debug!("Received unknown redraw event: {}", e);
             }
+            RedrawEvent::Malformed(e, err) => {
+                error!("Failed to parse redraw event '{}': {}", e, err);
+            }
         }
     }
 
@@ -874,7 +1084,12 @@ impl UIState {
         });
     }
 
-    fn handle_gnvim_event(&mut self, event: &GnvimEvent, nvim: &GioNeovim) {
+    fn handle_gnvim_event(
+        &mut self,
+        window: &gtk::ApplicationWindow,
+        event: &GnvimEvent,
+        nvim: &GioNeovim,
+    ) {
         match event {
             GnvimEvent::CompletionMenuToggleInfo => {
                 self.popupmenu.toggle_show_info()
@@ -904,6 +1119,27 @@ impl UIState {
                     nvim.clone(),
                 );
             }
+            GnvimEvent::EnableExtMessages(enable) => {
+                self.set_ui_option(
+                    "ext_messages".into(),
+                    *enable,
+                    nvim.clone(),
+                );
+            }
+            GnvimEvent::EnableExtMultigrid(enable) => {
+                self.set_ui_option(
+                    "ext_multigrid".into(),
+                    *enable,
+                    nvim.clone(),
+                );
+            }
+            GnvimEvent::NewWindow => {
+                if let Some(app) = window.get_application() {
+                    app.activate_action(NEW_WINDOW_ACTION, None);
+                }
+            }
+            GnvimEvent::Open => window.activate_action(OPEN_ACTION, None),
+            GnvimEvent::DirChanged(cwd) => self.set_cwd(window, cwd),
             GnvimEvent::Unknown(msg) => {
                 debug!("Received unknown GnvimEvent: {}", msg);
             }
@@ -972,7 +1208,9 @@ pub fn attach_grid_events(grid: &Grid, nvim: GioNeovim) {
         clone!(nvim => move |button, row, col| {
             let nvim = nvim.clone();
             spawn_local(async move {
-                nvim.input_mouse(&button.to_string(), "press", "", id, row as i64, col as i64).await.expect("Couldn't send mouse input");
+                if let Err(err) = nvim.input_mouse(&button.to_string(), "press", "", id, row as i64, col as i64).await {
+                    error!("Failed to send mouse input: {}", err);
+                }
             });
 
             Inhibit(false)
@@ -984,7 +1222,9 @@ pub fn attach_grid_events(grid: &Grid, nvim: GioNeovim) {
         clone!(nvim => move |button, row, col| {
             let nvim = nvim.clone();
             spawn_local(async move {
-                nvim.input_mouse(&button.to_string(), "release", "", id, row as i64, col as i64).await.expect("Couldn't send mouse input");
+                if let Err(err) = nvim.input_mouse(&button.to_string(), "release", "", id, row as i64, col as i64).await {
+                    error!("Failed to send mouse input: {}", err);
+                }
             });
 
             Inhibit(false)
@@ -996,32 +1236,29 @@ pub fn attach_grid_events(grid: &Grid, nvim: GioNeovim) {
         clone!(nvim => move |button, row, col| {
             let nvim = nvim.clone();
             spawn_local(async move {
-                nvim.input_mouse(&button.to_string(), "drag", "", id, row as i64, col as i64).await.expect("Couldn't send mouse input");
+                if let Err(err) = nvim.input_mouse(&button.to_string(), "drag", "", id, row as i64, col as i64).await {
+                    error!("Failed to send mouse input: {}", err);
+                }
             });
 
             Inhibit(false)
         }),
     );
 
-    // Scrolling events.
+    // Scrolling events. Nvim handles these even when 'mouse' is not set, so
+    // unlike the other mouse events, these are always sent.
     grid.connect_scroll_events(clone!(nvim => move |dir, row, col| {
         let nvim = nvim.clone();
         spawn_local(async move {
-            nvim.input_mouse("wheel", &dir.to_string(), "", id, row as i64, col as i64).await.expect("Couldn't send mouse input");
+            if let Err(err) = nvim.input_mouse("wheel", &dir.to_string(), "", id, row as i64, col as i64).await {
+                error!("Failed to send mouse input: {}", err);
+            }
         });
 
         Inhibit(false)
     }));
 }
 
-fn widget_show(widget: &gtk::Widget, show: bool) {
-    if show {
-        widget.show();
-    } else {
-        widget.hide();
-    }
-}
-
 fn win_float_anchor_pos(
     evt: &WindowFloatPos,
     anchor_metrics: &GridMetrics,
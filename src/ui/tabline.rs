@@ -33,6 +33,11 @@ pub struct Tabline {
     font: Font,
 
     line_space: i64,
+
+    /// If the tabline is enabled (`ext_tabline`).
+    enabled: bool,
+    /// When to show the tabline (`'showtabline'`).
+    showtabline: i64,
 }
 
 impl Tabline {
@@ -69,6 +74,8 @@ impl Tabline {
             colors: TablineColors::default(),
             font: Font::default(),
             line_space: 0,
+            enabled: true,
+            showtabline: 1,
         }
     }
 
@@ -87,11 +94,6 @@ impl Tabline {
         }
         glib::signal_handler_unblock(&self.notebook, &self.switch_tab_signal);
 
-        if tabs.len() < 2 {
-            self.notebook.hide();
-            return;
-        }
-
         glib::signal_handler_block(&self.notebook, &self.switch_tab_signal);
 
         let mut page = 0;
@@ -111,7 +113,7 @@ impl Tabline {
             }
         }
 
-        self.notebook.show_all();
+        self.update_visibility();
 
         self.notebook.set_current_page(Some(page as u32));
 
@@ -121,6 +123,32 @@ impl Tabline {
         glib::signal_handler_unblock(&self.notebook, &self.switch_tab_signal);
     }
 
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.update_visibility();
+    }
+
+    /// Sets when the tabline is shown. Same values as `'showtabline'`.
+    pub fn set_showtabline(&mut self, showtabline: i64) {
+        self.showtabline = showtabline;
+        self.update_visibility();
+    }
+
+    fn update_visibility(&self) {
+        let show = self.enabled
+            && match self.showtabline {
+                0 => false,
+                1 => self.notebook.get_n_pages() > 1,
+                _ => true,
+            };
+
+        if show {
+            self.notebook.show_all();
+        } else {
+            self.notebook.hide();
+        }
+    }
+
     pub fn set_font(&mut self, font: Font, hl_defs: &HlDefs) {
         self.font = font;
         self.set_styles(hl_defs);
@@ -0,0 +1,490 @@
+//! Tests for the UI, driven by a fake nvim.
+//!
+//! These need a display, so they're ignored by default. Run them with e.g.
+//! `xvfb-run cargo test -- --ignored`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gio::ApplicationExt;
+use gtk::prelude::*;
+use rmpv::Value;
+
+use crate::nvim_gio::fake::{run_for, run_on_main_thread, FakeNvim};
+use crate::ui::state::UIState;
+use crate::ui::ui::{SessionError, UI};
+
+/// Runs `f` against an UI that is connected to a fake nvim. Fails if gtk
+/// couldn't be initialized (e.g. there is no display).
+fn with_ui<F>(f: F)
+where
+    F: FnOnce(&FakeNvim, &Rc<RefCell<UIState>>, &gtk::ApplicationWindow)
+        + Send
+        + 'static,
+{
+    run_on_main_thread(|gtk| {
+        assert!(gtk, "Failed to initialize gtk, is there a display?");
+
+        let app = gtk::Application::new(
+            Some("com.github.vhakulinen.gnvim.tests"),
+            gio::ApplicationFlags::NON_UNIQUE,
+        )
+        .unwrap();
+        app.register(None::<&gio::Cancellable>).unwrap();
+
+        let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let (fake, nvim) = FakeNvim::start(tx).unwrap();
+
+        let ui = UI::init(
+            &app,
+            rx,
+            (640, 480),
+            nvim,
+            Box::new(|| {
+                Box::pin(async {
+                    Err(SessionError {
+                        message: String::from("Can't restart in tests"),
+                        stderr: String::new(),
+                    })
+                })
+            }),
+            false,
+            false,
+            true,
+        );
+        let state = ui.state();
+        let window = ui.window();
+        ui.start();
+
+        // Let the initial resizing settle down.
+        run_for(200);
+        fake.clear_calls();
+
+        f(&fake, &state, &window);
+
+        window.destroy();
+        run_for(10);
+    });
+}
+
+#[test]
+#[ignore]
+fn test_resize_sends_ui_try_resize_once() {
+    with_ui(|fake, _, window| {
+        window.resize(500, 400);
+        run_for(5);
+        window.resize(600, 400);
+
+        assert!(fake.wait_for_call(1000, "nvim_ui_try_resize"));
+        // Wait a bit more, in case there is another one coming.
+        run_for(100);
+
+        assert_eq!(fake.calls_to("nvim_ui_try_resize").len(), 1);
+    });
+}
+
+#[test]
+#[ignore]
+fn test_guifont_resizes_on_flush() {
+    with_ui(|fake, _, _| {
+        fake.redraw(vec![
+            Value::Array(vec![
+                "option_set".into(),
+                Value::Array(vec!["guifont".into(), "Monospace:h24".into()]),
+            ]),
+            Value::Array(vec!["flush".into()]),
+        ]);
+
+        assert!(fake.wait_for_call(1000, "nvim_ui_try_resize"));
+        assert_eq!(fake.calls_to("nvim_ui_try_resize").len(), 1);
+    });
+}
+
+#[test]
+#[ignore]
+fn test_tab_click_sets_current_tabpage() {
+    with_ui(|fake, state, _| {
+        let tab = |id| Value::Ext(2, vec![id]);
+        fake.redraw(vec![
+            Value::Array(vec![
+                "tabline_update".into(),
+                Value::Array(vec![
+                    tab(1),
+                    Value::Array(vec![
+                        Value::Map(vec![
+                            ("tab".into(), tab(1)),
+                            ("name".into(), "foo".into()),
+                        ]),
+                        Value::Map(vec![
+                            ("tab".into(), tab(2)),
+                            ("name".into(), "bar".into()),
+                        ]),
+                    ]),
+                ]),
+            ]),
+            Value::Array(vec!["flush".into()]),
+        ]);
+        run_for(50);
+
+        let notebook = state
+            .borrow()
+            .tabline
+            .get_widget()
+            .downcast::<gtk::Notebook>()
+            .unwrap();
+        notebook.set_current_page(Some(1));
+
+        assert!(fake.wait_for_call(1000, "nvim_set_current_tabpage"));
+        assert_eq!(
+            fake.calls_to("nvim_set_current_tabpage")
+                .into_iter()
+                .map(|call| call.args)
+                .collect::<Vec<_>>(),
+            vec![vec![tab(2)]]
+        );
+    });
+}
+
+#[test]
+#[ignore]
+fn test_showtabline_shows_single_tab() {
+    with_ui(|fake, state, _| {
+        let tab = Value::Ext(2, vec![1]);
+        let tabline_update = Value::Array(vec![
+            "tabline_update".into(),
+            Value::Array(vec![
+                tab.clone(),
+                Value::Array(vec![Value::Map(vec![
+                    ("tab".into(), tab),
+                    ("name".into(), "foo".into()),
+                ])]),
+            ]),
+        ]);
+        let notebook = state.borrow().tabline.get_widget();
+
+        fake.redraw(vec![
+            tabline_update.clone(),
+            Value::Array(vec!["flush".into()]),
+        ]);
+        run_for(50);
+        assert!(!notebook.is_visible());
+
+        fake.redraw(vec![
+            Value::Array(vec![
+                "option_set".into(),
+                Value::Array(vec!["showtabline".into(), 2.into()]),
+            ]),
+            tabline_update,
+            Value::Array(vec!["flush".into()]),
+        ]);
+        run_for(50);
+        assert!(notebook.is_visible());
+    });
+}
+
+#[test]
+#[ignore]
+fn test_confirm_dialog_sends_choice() {
+    with_ui(|fake, _, _| {
+        fake.redraw(vec![
+            Value::Array(vec![
+                "msg_show".into(),
+                Value::Array(vec![
+                    "confirm".into(),
+                    Value::Array(vec![Value::Array(vec![
+                        0.into(),
+                        "\nSave changes?\n[Y]es, (N)o, (C)ancel: ".into(),
+                    ])]),
+                    false.into(),
+                ]),
+            ]),
+            Value::Array(vec!["flush".into()]),
+        ]);
+        run_for(50);
+
+        let dialog = gtk::Window::list_toplevels()
+            .into_iter()
+            .find_map(|w| w.downcast::<gtk::MessageDialog>().ok())
+            .expect("No confirm dialog");
+        dialog.response(gtk::ResponseType::Other(1));
+
+        assert!(fake.wait_for_call(1000, "nvim_input"));
+        assert_eq!(
+            fake.calls_to("nvim_input")
+                .into_iter()
+                .map(|call| call.args)
+                .collect::<Vec<_>>(),
+            vec![vec![Value::from("n")]]
+        );
+    });
+}
+
+/// Redraw events for a multigrid window (grid 2) showing lines `topline` to
+/// `botline` of its buffer.
+fn window_viewport(
+    win: &Value,
+    topline: u64,
+    botline: u64,
+    line_count: Option<u64>,
+) -> Vec<Value> {
+    let mut viewport = vec![
+        2.into(),
+        win.clone(),
+        topline.into(),
+        botline.into(),
+        topline.into(),
+        0.into(),
+    ];
+    viewport.extend(line_count.map(Value::from));
+
+    vec![
+        Value::Array(vec![
+            "grid_resize".into(),
+            Value::Array(vec![2.into(), 40.into(), 30.into()]),
+        ]),
+        Value::Array(vec![
+            "win_pos".into(),
+            Value::Array(vec![
+                2.into(),
+                win.clone(),
+                0.into(),
+                0.into(),
+                40.into(),
+                30.into(),
+            ]),
+        ]),
+        Value::Array(vec!["win_viewport".into(), Value::Array(viewport)]),
+        Value::Array(vec!["flush".into()]),
+    ]
+}
+
+/// Scrollbar of the window on grid 2.
+fn window_scrollbar(state: &Rc<RefCell<UIState>>) -> gtk::Scrollbar {
+    state
+        .borrow()
+        .windows
+        .get(&2)
+        .expect("No window")
+        .scrollbar()
+        .widget()
+        .downcast::<gtk::Scrollbar>()
+        .unwrap()
+}
+
+#[test]
+#[ignore]
+fn test_scrollbar_scrolls_the_window() {
+    with_ui(|fake, state, _| {
+        let win = Value::Ext(1, vec![1]);
+        fake.redraw(window_viewport(&win, 0, 30, Some(100)));
+        run_for(50);
+
+        let scrollbar = window_scrollbar(state);
+        assert!(scrollbar.is_visible());
+        // Applying the viewport doesn't scroll nvim.
+        assert!(fake.calls_to("nvim_call_function").is_empty());
+
+        scrollbar.get_adjustment().set_value(20.0);
+
+        assert!(fake.wait_for_call(1000, "nvim_call_function"));
+        assert_eq!(
+            fake.calls_to("nvim_call_function")
+                .into_iter()
+                .map(|call| call.args)
+                .collect::<Vec<_>>(),
+            vec![vec![
+                "win_execute".into(),
+                Value::Array(vec![
+                    win,
+                    "call winrestview({'topline': 21})".into()
+                ]),
+            ]]
+        );
+    });
+}
+
+#[test]
+#[ignore]
+fn test_scrollbar_asks_for_missing_line_count() {
+    with_ui(|fake, state, _| {
+        let win = Value::Ext(1, vec![1]);
+        fake.redraw(window_viewport(&win, 0, 30, None));
+
+        assert!(fake.wait_for_call(1000, "nvim_call_function"));
+        assert_eq!(
+            fake.calls_to("nvim_call_function")
+                .into_iter()
+                .map(|call| call.args)
+                .collect::<Vec<_>>(),
+            vec![vec!["line".into(), Value::Array(vec!["$".into(), win]),]]
+        );
+        run_for(50);
+
+        let scrollbar = window_scrollbar(state);
+        assert!(scrollbar.is_visible());
+        assert!(
+            (scrollbar.get_adjustment().get_upper() - 100.0).abs()
+                < f64::EPSILON
+        );
+    });
+}
+
+#[test]
+#[ignore]
+fn test_single_grid_scrollbar_follows_current_window() {
+    with_ui(|fake, state, _| {
+        fake.redraw(vec![
+            Value::Array(vec![
+                "option_set".into(),
+                Value::Array(vec!["ext_multigrid".into(), false.into()]),
+            ]),
+            Value::Array(vec!["flush".into()]),
+        ]);
+
+        // Nvim doesn't send `win_viewport` without multigrid.
+        assert!(fake.wait_for_call(1000, "nvim_eval"));
+        run_for(50);
+
+        let scrollbar = state
+            .borrow()
+            .scrollbar
+            .widget()
+            .downcast::<gtk::Scrollbar>()
+            .unwrap();
+        assert!(scrollbar.is_visible());
+        assert!(
+            (scrollbar.get_adjustment().get_upper() - 100.0).abs()
+                < f64::EPSILON
+        );
+
+        scrollbar.get_adjustment().set_value(20.0);
+        assert!(fake.wait_for_call(1000, "nvim_call_function"));
+        assert_eq!(
+            fake.calls_to("nvim_call_function")[0].args,
+            vec![
+                "win_execute".into(),
+                Value::Array(vec![
+                    1000.into(),
+                    "call winrestview({'topline': 21})".into()
+                ]),
+            ]
+        );
+    });
+}
+
+#[test]
+#[ignore]
+fn test_dir_changed_sets_subtitle() {
+    with_ui(|fake, state, window| {
+        fake.notify("Gnvim", vec!["DirChanged".into(), "/tmp".into()]);
+        run_for(50);
+
+        let header_bar = window
+            .get_titlebar()
+            .and_then(|w| w.downcast::<gtk::HeaderBar>().ok())
+            .unwrap();
+        assert_eq!(
+            header_bar.get_subtitle().as_ref().map(|s| s.as_str()),
+            Some("/tmp")
+        );
+        assert_eq!(
+            *state.borrow().cwd.borrow(),
+            Some(std::path::PathBuf::from("/tmp"))
+        );
+    });
+}
+
+#[test]
+#[ignore]
+fn test_single_grid_mode_ignores_windows() {
+    with_ui(|fake, state, _| {
+        fake.redraw(vec![
+            Value::Array(vec![
+                "option_set".into(),
+                Value::Array(vec!["ext_multigrid".into(), false.into()]),
+            ]),
+            Value::Array(vec![
+                "grid_resize".into(),
+                Value::Array(vec![2.into(), 20.into(), 10.into()]),
+            ]),
+            Value::Array(vec![
+                "win_pos".into(),
+                Value::Array(vec![
+                    2.into(),
+                    Value::Ext(1, vec![1]),
+                    0.into(),
+                    0.into(),
+                    20.into(),
+                    10.into(),
+                ]),
+            ]),
+            Value::Array(vec!["flush".into()]),
+        ]);
+        run_for(50);
+
+        let state = state.borrow();
+        assert!(!state.multigrid);
+        assert!(state.windows.is_empty());
+        assert_eq!(state.grids.keys().collect::<Vec<_>>(), vec![&1]);
+    });
+}
+
+#[test]
+#[ignore]
+fn test_close_asks_nvim_to_quit() {
+    with_ui(|fake, _, window| {
+        window.close();
+
+        assert!(fake.wait_for_call(1000, "nvim_command"));
+        assert_eq!(
+            fake.calls_to("nvim_command")
+                .into_iter()
+                .map(|call| call.args)
+                .collect::<Vec<_>>(),
+            vec![vec![Value::from("confirm qa")]]
+        );
+        // Nvim gets to decide if we're closing.
+        assert!(window.is_visible());
+    });
+}
+
+#[test]
+#[ignore]
+fn test_close_asks_again_after_cancel() {
+    with_ui(|fake, _, window| {
+        window.close();
+        assert!(fake.wait_for_call(1000, "nvim_command"));
+        // Let the (canceled) `confirm qa` return.
+        run_for(50);
+
+        window.close();
+        assert!(fake.wait_for(1000, |calls| {
+            calls
+                .iter()
+                .filter(|call| call.method == "nvim_command")
+                .count()
+                == 2
+        }));
+        assert!(window.is_visible());
+    });
+}
+
+#[test]
+#[ignore]
+fn test_open_starts_in_cwd() {
+    with_ui(|fake, _, _| {
+        fake.notify("Gnvim", vec!["DirChanged".into(), "/tmp".into()]);
+        fake.notify("Gnvim", vec!["Open".into()]);
+        run_for(50);
+
+        let dialog = gtk::Window::list_toplevels()
+            .into_iter()
+            .find_map(|w| w.downcast::<gtk::FileChooserDialog>().ok())
+            .expect("No file chooser");
+        assert_eq!(
+            dialog.get_current_folder(),
+            Some(std::path::PathBuf::from("/tmp"))
+        );
+        dialog.response(gtk::ResponseType::Cancel);
+    });
+}
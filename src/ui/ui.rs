@@ -1,30 +1,275 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use futures::future::LocalBoxFuture;
+use gdk::WindowExt;
+use gio::{ActionMapExt, FileExt};
 use gtk::prelude::*;
 
 use log::{debug, error};
 use rmpv::Value;
 
-use crate::nvim_bridge::{Message, Request};
+use crate::nvim_bridge::{self, Message, Request};
 use crate::nvim_gio::GioNeovim;
 use crate::ui::cmdline::Cmdline;
 use crate::ui::color::{Highlight, HlDefs};
-use crate::ui::common::spawn_local;
+use crate::ui::common::{escape_filename, spawn_local};
 #[cfg(feature = "libwebkit2gtk")]
 use crate::ui::cursor_tooltip::CursorTooltip;
+use crate::ui::error_dialog::show_error;
 use crate::ui::font::Font;
 use crate::ui::grid::Grid;
+use crate::ui::messages::Messages;
 use crate::ui::popupmenu::Popupmenu;
+use crate::ui::prompt::Prompt;
+use crate::ui::scrollbar::Scrollbar;
+use crate::ui::session_ended::SessionEnded;
 use crate::ui::state::{attach_grid_events, UIState, Windows};
 use crate::ui::tabline::Tabline;
 use crate::ui::window::MsgWindow;
 
-/// Main UI structure.
-pub struct UI {
+/// Name of the application action that opens a new window.
+pub const NEW_WINDOW_ACTION: &str = "new-window";
+
+/// Name of the window action that lets the user choose files to open.
+pub const OPEN_ACTION: &str = "open";
+
+/// Hint for `gio::Application::open` to open the files in a new tab.
+pub const OPEN_HINT_TAB: &str = "tab";
+
+thread_local! {
+    /// Shells of the open windows. A shell is kept here until its window is
+    /// destroyed, so it outlives the sessions it shows.
+    static SHELLS: RefCell<Vec<Rc<Shell>>> = RefCell::new(vec![]);
+}
+
+/// Failure to start a new nvim session.
+pub struct SessionError {
+    /// What went wrong.
+    pub message: String,
+    /// What nvim wrote to its stderr.
+    pub stderr: String,
+}
+
+/// Starts a new nvim session. Returns the channel to receive the session's
+/// events from and the nvim instance itself.
+pub type SessionStarter = Box<
+    dyn Fn() -> LocalBoxFuture<
+        'static,
+        Result<(glib::Receiver<Message>, GioNeovim), SessionError>,
+    >,
+>;
+
+/// Parts of the UI that outlive a single nvim session.
+struct Shell {
     /// Main window.
     win: gtk::ApplicationWindow,
+    /// Holds the editor and the "session ended" view.
+    stack: gtk::Stack,
+    /// View to show when nvim goes away.
+    session_ended: SessionEnded,
+    /// Used to start a new session once the previous one has ended.
+    starter: SessionStarter,
+    /// Nvim of the current session, if it's still running.
+    nvim: RefCell<Option<GioNeovim>>,
+    /// Source that receives the current session's events from nvim.
+    session_source: RefCell<Option<glib::SourceId>>,
+    /// If we're attached to an external nvim.
+    attached: bool,
+    /// If nvim runs on another machine (see `--ssh`).
+    remote: bool,
+    /// If we ask nvim for `ext_multigrid`.
+    multigrid: bool,
+    /// Nvim's current directory, for resolving files dropped on the window.
+    /// Stays unset for remote nvim, since its paths aren't ours.
+    cwd: Rc<RefCell<Option<PathBuf>>>,
+    /// If the user has asked to close the window, and we're waiting for nvim
+    /// to quit.
+    closing: Cell<bool>,
+    /// Styles the main window. Shared by all sessions, so restarting doesn't
+    /// pile up providers on the window.
+    css_provider: gtk::CssProvider,
+}
+
+impl Shell {
+    /// Shows the "session ended" view with `reason`.
+    fn end_session(&self, reason: &str) {
+        self.session_ended.set_reason(reason);
+        self.stack.set_visible_child_name("session-ended");
+    }
+
+    /// Shows an error dialog on top of the main window.
+    fn show_error(&self, message: &str, details: &str) {
+        if let Some(app) = self.win.get_application() {
+            show_error(&app, Some(self.win.upcast_ref()), message, details);
+        }
+    }
+
+    /// Opens `files` in the current session with `:drop`.
+    ///
+    /// * `new_tab` - Open the files in a new tab.
+    fn open_files(&self, files: &[gio::File], new_tab: bool) {
+        let nvim = match *self.nvim.borrow() {
+            Some(ref nvim) => nvim.clone(),
+            None => return,
+        };
+
+        let cmd = if new_tab { "tab drop" } else { "drop" };
+        let cwd = self.cwd.borrow().clone();
+        let cmds: Vec<String> = files
+            .iter()
+            .map(|file| {
+                let name = match file.get_path() {
+                    // Relative to nvim's cwd, so the buffer names stay short.
+                    Some(path) => cwd
+                        .as_ref()
+                        .and_then(|cwd| path.strip_prefix(cwd).ok())
+                        .unwrap_or_else(|| path.as_path())
+                        .to_string_lossy()
+                        .into_owned(),
+                    // Not a local file, let nvim (e.g. netrw) handle the uri.
+                    None => file.get_uri().to_string(),
+                };
+                format!("{} {}", cmd, escape_filename(&name))
+            })
+            .collect();
+
+        spawn_local(async move {
+            for cmd in cmds {
+                if let Err(err) = nvim.command(&cmd).await {
+                    error!("Failed to open file: {}", err);
+                }
+            }
+        });
+
+        self.win.present();
+    }
+
+    /// Asks nvim that we've spawned to quit when the user closes the window.
+    /// Nvim gets to confirm about unsaved changes, and the window closes once
+    /// nvim is gone. Returns true if the window should stay open for now.
+    ///
+    /// If nvim doesn't answer (e.g. it's hung or waiting in another prompt),
+    /// closing the window again closes it for good.
+    fn request_close(shell: &Rc<Shell>) -> bool {
+        if shell.attached || shell.closing.get() {
+            return false;
+        }
+
+        let nvim = match *shell.nvim.borrow() {
+            Some(ref nvim) => nvim.clone(),
+            None => return false,
+        };
+
+        shell.closing.set(true);
+        let shell_weak = Rc::downgrade(shell);
+        spawn_local(async move {
+            let res = nvim.command("confirm qa").await;
+            let shell = upgrade_weak!(shell_weak);
+
+            match res {
+                // Nvim is still running, so the user canceled.
+                Ok(_) => shell.closing.set(false),
+                Err(err) => {
+                    error!("Failed to quit nvim: {}", err);
+                    if shell.closing.get() {
+                        shell.win.close();
+                    }
+                }
+            }
+        });
+
+        true
+    }
+
+    /// Lets the user choose files to open with a file chooser, which starts
+    /// in nvim's current directory.
+    fn choose_files(shell: &Rc<Shell>) {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Open Files"),
+            Some(&shell.win),
+            gtk::FileChooserAction::Open,
+            &[
+                ("_Cancel", gtk::ResponseType::Cancel),
+                ("_Open", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.set_select_multiple(true);
+        dialog.set_default_response(gtk::ResponseType::Accept);
+        if let Some(ref cwd) = *shell.cwd.borrow() {
+            dialog.set_current_folder(cwd);
+        }
+
+        let shell_weak = Rc::downgrade(shell);
+        dialog.connect_response(move |dialog, res| {
+            let files = dialog.get_files();
+            dialog.destroy();
+
+            if res == gtk::ResponseType::Accept {
+                let shell = upgrade_weak!(shell_weak);
+                shell.open_files(&files, false);
+            }
+        });
+
+        dialog.show();
+    }
+
+    /// Ends the current session when the window is destroyed. Nvim that we've
+    /// spawned is told to quit, so it doesn't linger around without a
+    /// window. Unsaved changes are preserved in the swap files, for
+    /// recovering them later. External nvim is just detached from.
+    fn close_session(&self) {
+        let nvim = match self.nvim.borrow_mut().take() {
+            Some(nvim) => nvim,
+            None => return,
+        };
+
+        // Nothing is shown from the session anymore.
+        if let Some(source_id) = self.session_source.borrow_mut().take() {
+            glib::source::source_remove(source_id);
+        }
+
+        let attached = self.attached;
+        spawn_local(async move {
+            let res = if attached {
+                close_channel(&nvim).await
+            } else {
+                nvim.command(
+                    "if !empty(getbufinfo({'bufmodified': 1})) \
+                     | silent! preserve | endif | qa!",
+                )
+                .await
+            };
+
+            if let Err(err) = res {
+                debug!("Failed to close nvim session: {}", err);
+            }
+        });
+    }
+
+    /// Starts a new session and replaces the previous editor with it.
+    fn restart(shell: Rc<Shell>) {
+        shell.session_ended.set_starting();
+
+        let session = (shell.starter)();
+        spawn_local(async move {
+            match session.await {
+                Ok((rx, nvim)) => UI::new_session(shell, rx, nvim).start(),
+                Err(err) => {
+                    shell.session_ended.set_reason(&err.message);
+                    shell.show_error(&err.message, &err.stderr);
+                }
+            }
+        });
+    }
+}
+
+/// Main UI structure.
+pub struct UI {
+    /// Main window and other session independent parts.
+    shell: Rc<Shell>,
     /// Neovim instance.
     nvim: GioNeovim,
     /// Channel to receive event from nvim.
@@ -32,6 +277,8 @@ pub struct UI {
     /// Our internal state, containing basically everything we manipulate
     /// when we receive an event from nvim.
     state: Rc<RefCell<UIState>>,
+    /// Signal handlers connected to the main window for the current session.
+    win_signals: Vec<glib::SignalHandlerId>,
 }
 
 impl UI {
@@ -41,23 +288,141 @@ impl UI {
     /// * `rx` - Channel to receive nvim UI events.
     /// * `nvim` - Neovim instance to use. Should be the same that is the source
     ///            of `rx` events.
+    /// * `starter` - Starts a new session when the user asks for it after
+    ///               nvim has gone away.
+    /// * `reconnect` - If we're attached to an external nvim.
+    /// * `remote` - If nvim runs on another machine.
+    /// * `multigrid` - If `ext_multigrid` is enabled.
+    #[allow(clippy::too_many_arguments)]
     pub fn init(
         app: &gtk::Application,
         rx: glib::Receiver<Message>,
         window_size: (i32, i32),
         nvim: GioNeovim,
+        starter: SessionStarter,
+        reconnect: bool,
+        remote: bool,
+        multigrid: bool,
     ) -> Self {
         // Create the main window.
         let window = gtk::ApplicationWindow::new(app);
         window.set_title("Neovim");
         window.set_default_size(window_size.0, window_size.1);
 
+        // Title and nvim's cwd (as the subtitle) are shown in the header bar.
+        let header_bar = gtk::HeaderBar::new();
+        header_bar.set_show_close_button(true);
+        header_bar.set_title(Some("Neovim"));
+        header_bar.show();
+        window.set_titlebar(Some(&header_bar));
+
         // Realize window resources.
         window.realize();
 
+        let stack = gtk::Stack::new();
+        window.add(&stack);
+
+        let session_ended = SessionEnded::new(reconnect);
+        stack.add_named(&session_ended.widget(), "session-ended");
+        session_ended.connect_quit(clone!(window => move || window.close()));
+
+        let css_provider = gtk::CssProvider::new();
+        add_css_provider!(&css_provider, window);
+
+        let shell = Rc::new(Shell {
+            win: window,
+            stack,
+            session_ended,
+            starter,
+            nvim: RefCell::new(None),
+            session_source: RefCell::new(None),
+            attached: reconnect,
+            remote,
+            multigrid,
+            cwd: Rc::new(RefCell::new(None)),
+            closing: Cell::new(false),
+            css_provider,
+        });
+
+        SHELLS.with(|shells| shells.borrow_mut().push(shell.clone()));
+
+        // Other windows might still be open, so our nvim won't go away with
+        // the process.
+        let shell_weak = Rc::downgrade(&shell);
+        shell.win.connect_destroy(move |_| {
+            let shell = upgrade_weak!(shell_weak);
+            shell.close_session();
+            SHELLS.with(|shells| {
+                shells.borrow_mut().retain(|s| !Rc::ptr_eq(s, &shell))
+            });
+        });
+
+        let shell_weak = Rc::downgrade(&shell);
+        shell.win.connect_delete_event(move |_, _| {
+            let shell = upgrade_weak!(shell_weak, Inhibit(false));
+            Inhibit(Shell::request_close(&shell))
+        });
+
+        let shell_weak = Rc::downgrade(&shell);
+        shell.session_ended.connect_restart(move || {
+            let shell = upgrade_weak!(shell_weak);
+            Shell::restart(shell);
+        });
+
+        let open = gio::SimpleAction::new(OPEN_ACTION, None);
+        let shell_weak = Rc::downgrade(&shell);
+        open.connect_activate(move |_, _| {
+            let shell = upgrade_weak!(shell_weak);
+            Shell::choose_files(&shell);
+        });
+        shell.win.add_action(&open);
+
+        // Open files dropped on the window.
+        shell.win.drag_dest_set(
+            gtk::DestDefaults::ALL,
+            &[gtk::TargetEntry::new(
+                "text/uri-list",
+                gtk::TargetFlags::OTHER_APP,
+                0,
+            )],
+            gdk::DragAction::COPY,
+        );
+        let shell_weak = Rc::downgrade(&shell);
+        shell
+            .win
+            .connect_drag_data_received(move |_, _, _, _, data, _, _| {
+                let shell = upgrade_weak!(shell_weak);
+                let files: Vec<gio::File> = data
+                    .get_uris()
+                    .iter()
+                    .map(|uri| gio::File::new_for_uri(uri))
+                    .collect();
+                shell.open_files(&files, false);
+            });
+
+        UI::new_session(shell, rx, nvim)
+    }
+
+    /// Builds the editor for a new nvim session. Replaces the editor of the
+    /// previous session, if any.
+    fn new_session(
+        shell: Rc<Shell>,
+        rx: glib::Receiver<Message>,
+        nvim: GioNeovim,
+    ) -> Self {
+        let window = shell.win.clone();
+        shell.nvim.replace(Some(nvim.clone()));
+        shell.cwd.replace(None);
+        set_subtitle(&window, None);
+
+        // Remove the editor of the previous session.
+        if let Some(editor) = shell.stack.get_child_by_name("editor") {
+            shell.stack.remove(&editor);
+        }
+
         // Top level widget.
         let b = gtk::Box::new(gtk::Orientation::Vertical, 0);
-        window.add(&b);
+        shell.stack.add_named(&b, "editor");
 
         let tabline = Tabline::new(nvim.clone());
         b.pack_start(&tabline.get_widget(), false, false, 0);
@@ -89,6 +454,8 @@ impl UI {
         // Mark the default grid as active at the beginning.
         grid.set_active(true);
         overlay.add(&grid.widget());
+        let scrollbar = Scrollbar::new(nvim.clone());
+        overlay.add_overlay(&scrollbar.widget());
 
         let windows_container = gtk::Fixed::new();
         windows_container.set_widget_name("windows-contianer");
@@ -100,7 +467,7 @@ impl UI {
         overlay.add_overlay(&msg_window_container);
         overlay.add_overlay(&windows_float_container);
 
-        let css_provider = gtk::CssProvider::new();
+        let css_provider = shell.css_provider.clone();
         let msg_window =
             MsgWindow::new(msg_window_container.clone(), css_provider.clone());
 
@@ -155,18 +522,31 @@ impl UI {
 
             let nvim = nvim.clone();
             spawn_local(async move {
-                nvim.input(&nvim_input).await.expect("Couldn't send input");
+                if let Err(err) = nvim.input(&nvim_input).await {
+                    error!("Failed to send input: {}", err);
+                }
             });
         }));
 
-        window.connect_key_press_event(clone!(nvim, im_context => move |_, e| {
+        let mut win_signals = vec![];
+
+        // Same as nvim's default for 'mousehide'.
+        let mousehide = Rc::new(Cell::new(true));
+
+        win_signals.push(window.connect_key_press_event(clone!(nvim, im_context, mousehide => move |window, e| {
+            if mousehide.get() {
+                set_pointer_hidden(window, true);
+            }
+
             if im_context.filter_keypress(e) {
                 Inhibit(true)
             } else {
                 if let Some(input) = event_to_nvim_input(e) {
                     let nvim = nvim.clone();
                     spawn_local(async move {
-                        nvim.input(input.as_str()).await.expect("Couldn't send input");
+                        if let Err(err) = nvim.input(input.as_str()).await {
+                            error!("Failed to send input: {}", err);
+                        }
                     });
                     return Inhibit(true);
                 } else {
@@ -178,42 +558,68 @@ impl UI {
 
                 Inhibit(false)
             }
-        }));
+        })));
 
-        window.connect_key_release_event(clone!(im_context => move |_, e| {
-            im_context.filter_keypress(e);
+        // Bring back the pointer hidden by 'mousehide'.
+        window.add_events(gdk::EventMask::POINTER_MOTION_MASK);
+        win_signals.push(window.connect_motion_notify_event(|window, _| {
+            set_pointer_hidden(window, false);
             Inhibit(false)
         }));
 
-        window.connect_focus_in_event(clone!(im_context => move |_, _| {
-            im_context.focus_in();
-            Inhibit(false)
-        }));
+        win_signals.push(window.connect_key_release_event(
+            clone!(im_context => move |_, e| {
+                im_context.filter_keypress(e);
+                Inhibit(false)
+            }),
+        ));
+
+        win_signals.push(window.connect_focus_in_event(
+            clone!(im_context => move |window, _| {
+                // Clear the urgency hint set by the bell.
+                window.set_urgency_hint(false);
+                im_context.focus_in();
+                Inhibit(false)
+            }),
+        ));
 
-        window.connect_focus_out_event(clone!(im_context => move |_, _| {
-            im_context.focus_out();
-            Inhibit(false)
-        }));
+        win_signals.push(window.connect_focus_out_event(
+            clone!(im_context => move |_, _| {
+                im_context.focus_out();
+                Inhibit(false)
+            }),
+        ));
 
         let cmdline = Cmdline::new(&overlay, nvim.clone());
+        let mut messages = Messages::new(&overlay);
         #[cfg(feature = "libwebkit2gtk")]
         let cursor_tooltip = CursorTooltip::new(&overlay);
 
         window.show_all();
+        shell.stack.set_visible_child_name("editor");
 
         grid.set_im_context(&im_context);
 
         cmdline.hide();
+        messages.hide();
         #[cfg(feature = "libwebkit2gtk")]
         cursor_tooltip.hide();
 
         let mut grids = HashMap::new();
         grids.insert(1, grid);
 
-        add_css_provider!(&css_provider, window);
+        // Nvim tells if it doesn't support multigrid after all.
+        let multigrid = shell.multigrid;
+
+        // Remote nvim's cwd is only shown, not used for our files.
+        let cwd = if shell.remote {
+            Rc::new(RefCell::new(None))
+        } else {
+            shell.cwd.clone()
+        };
 
         UI {
-            win: window,
+            shell,
             rx,
             state: Rc::new(RefCell::new(UIState {
                 css_provider,
@@ -230,6 +636,9 @@ impl UI {
                 cmdline,
                 overlay,
                 tabline,
+                messages,
+                prompt: Prompt::new(&window, nvim.clone()),
+                scrollbar,
                 #[cfg(feature = "libwebkit2gtk")]
                 cursor_tooltip,
                 resize_source_id: source_id,
@@ -240,48 +649,166 @@ impl UI {
                 line_space,
                 current_mode: None,
                 enable_cursor_animations: true,
+                mouse_enabled: true,
+                mousehide,
+                cwd,
+                font_wide: None,
+                multigrid,
             })),
             nvim,
+            win_signals,
         }
     }
 
+    /// Our internal state, for tests to inspect and poke at.
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> Rc<RefCell<UIState>> {
+        self.state.clone()
+    }
+
+    /// The main window.
+    #[cfg(test)]
+    pub(crate) fn window(&self) -> gtk::ApplicationWindow {
+        self.shell.win.clone()
+    }
+
     /// Starts to listen events from `rx` (e.g. from nvim) and processing those.
     /// Think this as the "main" function of the UI.
     pub fn start(self) {
         let UI {
             rx,
             state,
-            win,
+            shell,
             nvim,
+            mut win_signals,
         } = self;
 
-        rx.attach(None, move |message| {
+        let source_shell = shell.clone();
+        let source_id = rx.attach(None, move |message| {
             match message {
                 // Handle a notify.
                 Message::Notify(notify) => {
                     let mut state = state.borrow_mut();
 
-                    state.handle_notify(&win, notify, &nvim);
+                    state.handle_notify(&shell.win, notify, &nvim);
                 }
                 // Handle a request.
                 Message::Request(tx, request) => {
                     let mut state = state.borrow_mut();
                     let res = handle_request(&request, &mut state);
-                    tx.send(res).expect("Failed to respond to a request");
+                    if let Err(err) = tx.send(res) {
+                        error!("Failed to respond to a request: {}", err);
+                    }
                 }
                 // Handle close.
-                Message::Close => {
-                    win.close();
+                Message::Close(info) => {
+                    // Detach the session from the main window. Rest of the
+                    // session gets dropped along with this closure.
+                    shell.session_source.replace(None);
+                    for id in win_signals.drain(..) {
+                        shell.win.disconnect(id);
+                    }
+                    shell.nvim.replace(None);
+
+                    // Nvim quit because the window was closed.
+                    if shell.closing.replace(false) && info.stderr.is_none() {
+                        shell.win.close();
+                        return Continue(false);
+                    }
+
+                    shell.end_session(&info.reason);
+                    if let Some(ref stderr) = info.stderr {
+                        shell.show_error(&info.reason, stderr);
+                    }
                     return Continue(false);
                 }
             }
 
             Continue(true)
         });
+        source_shell.session_source.replace(Some(source_id));
     }
 }
 
-#[cfg_attr(not(feature = "libwebkit2gtk"), allow(unused_variables))] // Silence clippy
+/// Opens `files` in the most recently focused window that has a running
+/// session. Returns false if there's no such window.
+///
+/// * `hint` - Hint passed to `gio::Application::open`, see `OPEN_HINT_TAB`.
+pub fn open_files(
+    app: &gtk::Application,
+    files: &[gio::File],
+    hint: &str,
+) -> bool {
+    // Windows are sorted by the most recently focused one.
+    let shell = SHELLS.with(|shells| {
+        let shells = shells.borrow();
+        app.get_windows().into_iter().find_map(|win| {
+            shells
+                .iter()
+                .find(|shell| {
+                    shell.nvim.borrow().is_some()
+                        && shell.win.clone().upcast::<gtk::Window>() == win
+                })
+                .cloned()
+        })
+    });
+
+    match shell {
+        Some(shell) => {
+            shell.open_files(files, hint == OPEN_HINT_TAB);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Detaches from external nvim and closes our channel to it, so nvim doesn't
+/// keep sending us events.
+async fn close_channel(
+    nvim: &GioNeovim,
+) -> Result<(), Box<nvim_rs::error::CallError>> {
+    let api_info = nvim.get_api_info().await?;
+    nvim.ui_detach().await?;
+    // Nvim closes the channel before it gets to respond.
+    let _ = nvim
+        .call_function("chanclose", vec![api_info[0].clone()])
+        .await;
+
+    Ok(())
+}
+
+/// Sets the subtitle of `window`'s header bar.
+pub fn set_subtitle(window: &gtk::ApplicationWindow, subtitle: Option<&str>) {
+    if let Some(header_bar) = window
+        .get_titlebar()
+        .and_then(|w| w.downcast::<gtk::HeaderBar>().ok())
+    {
+        header_bar.set_subtitle(subtitle);
+    }
+}
+
+/// Hides (or shows) the mouse pointer when it's on top of `window`.
+fn set_pointer_hidden(window: &gtk::ApplicationWindow, hidden: bool) {
+    let gdk_window = match window.get_window() {
+        Some(gdk_window) => gdk_window,
+        None => return,
+    };
+
+    if gdk_window.get_cursor().is_some() == hidden {
+        return;
+    }
+
+    let cursor = if hidden {
+        Some(gdk::Cursor::new_for_display(
+            &gdk_window.get_display(),
+            gdk::CursorType::BlankCursor,
+        ))
+    } else {
+        None
+    };
+    gdk_window.set_cursor(cursor.as_ref());
+}
+
 fn handle_request(
     request: &Request,
     state: &mut UIState,
@@ -300,6 +827,21 @@ fn handle_request(
         Request::CursorTooltipStyles => {
             Err("Cursor tooltip is not supported in this build".into())
         }
+        Request::ApiInfo => Ok(nvim_bridge::api_info()),
+        Request::CursorHlNames => {
+            let names = state
+                .grids
+                .get(&state.current_grid)
+                .and_then(|grid| grid.cursor_hl_id())
+                .map(|id| state.hl_defs.get_names(&id))
+                .unwrap_or_default();
+
+            Ok(names
+                .into_iter()
+                .map(Value::from)
+                .collect::<Vec<_>>()
+                .into())
+        }
     }
 }
 
@@ -2,8 +2,10 @@ use gtk::prelude::*;
 
 use nvim_rs::Window as NvimWindow;
 
-use crate::nvim_gio::GioWriter;
+use crate::nvim_bridge::WinViewport;
+use crate::nvim_gio::{GioNeovim, GioWriter};
 use crate::ui::grid::Grid;
+use crate::ui::scrollbar::Scrollbar;
 
 pub struct MsgWindow {
     fixed: gtk::Fixed,
@@ -64,11 +66,23 @@ impl MsgWindow {
         );
         self.fixed.show_all();
     }
+
+    /// Removes the grid from the message window, e.g. when the grid is gone
+    /// because `ext_multigrid` was disabled.
+    pub fn clear(&self) {
+        if let Some(child) = self.frame.get_child() {
+            self.frame.remove(&child);
+        }
+        self.frame.hide();
+    }
 }
 
 pub struct Window {
     fixed: gtk::Fixed,
     frame: gtk::Frame,
+    /// Container for the grid and the scrollbar.
+    overlay: gtk::Overlay,
+    scrollbar: Scrollbar,
 
     external_win: Option<gtk::Window>,
 
@@ -86,12 +100,19 @@ impl Window {
         fixed: gtk::Fixed,
         grid: &Grid,
         css_provider: Option<gtk::CssProvider>,
+        nvim: GioNeovim,
     ) -> Self {
         let frame = gtk::Frame::new(None);
         fixed.put(&frame, 0, 0);
 
+        let overlay = gtk::Overlay::new();
+        frame.add(&overlay);
+
         let widget = grid.widget();
-        frame.add(&widget);
+        overlay.add(&widget);
+
+        let scrollbar = Scrollbar::new(nvim);
+        overlay.add_overlay(&scrollbar.widget());
 
         if let Some(css_provider) = css_provider {
             add_css_provider!(&css_provider, frame);
@@ -100,6 +121,8 @@ impl Window {
         Self {
             fixed,
             frame,
+            overlay,
+            scrollbar,
             external_win: None,
             grid_id: grid.id,
             nvim_win: win,
@@ -163,6 +186,16 @@ impl Window {
         self.frame.show_all();
     }
 
+    pub fn set_viewport(&self, viewport: &WinViewport) {
+        self.scrollbar.set_viewport(viewport);
+    }
+
+    /// The window's scrollbar, for tests to poke at.
+    #[cfg(test)]
+    pub fn scrollbar(&self) -> &Scrollbar {
+        &self.scrollbar
+    }
+
     pub fn hide(&self) {
         self.frame.hide();
     }
@@ -170,10 +203,10 @@ impl Window {
 
 impl Drop for Window {
     fn drop(&mut self) {
-        if let Some(child) = self.frame.get_child() {
+        if let Some(child) = self.overlay.get_child() {
             // We don't want to destroy the child widget, so just remove the child from our
             // container.
-            self.frame.remove(&child);
+            self.overlay.remove(&child);
         }
 
         self.fixed.remove(&self.frame);